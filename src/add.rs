@@ -0,0 +1,328 @@
+use std::{fs, path::Path};
+
+use git2::Repository;
+use toml_edit::{table, value, Array, Document, Item, Value};
+
+use crate::{
+    error::{FatalError, FatalResult},
+    progress,
+    project::{parse_spork_file, ProjectFile, ProjectType},
+    success,
+    targets::Target,
+    util::{mkdir_all, mkfile},
+    warning, SPORK_FILE_NAME,
+};
+
+/// Directory that git dependencies are cloned into, relative to the project.
+const GIT_DEPS_DIR: &str = "deps";
+
+/// Adds a library to the project's `dependencies`, cloning it into `deps/` first if it's a git
+/// URL. Features enabled for it are written to `dependency_features`.
+pub fn add(source: &str, features: &[String]) -> FatalResult<()> {
+    let spork_file = parse_spork_file(SPORK_FILE_NAME)?;
+    let mut doc = read_document()?;
+
+    let (path, cloned) = if is_git_url(source) {
+        (clone_dependency(source)?, true)
+    } else {
+        (
+            source.trim_end_matches(['/', '\\']).replace('\\', "/"),
+            false,
+        )
+    };
+
+    let name = match check_dependency(&path, &spork_file, features) {
+        Ok(res) => res,
+        Err(err) => {
+            if cloned {
+                let _ = fs::remove_dir_all(&path);
+            }
+            return Err(err);
+        }
+    };
+
+    let project = match doc["project"].as_table_mut() {
+        Some(res) => res,
+        None => return Err(FatalError::InvalidSporkToml),
+    };
+
+    if !project.contains_key("dependencies") {
+        project.insert("dependencies", Item::Value(Value::Array(Array::new())));
+    }
+
+    let Some(deps) = project["dependencies"].as_array_mut() else {
+        return Err(FatalError::InvalidSporkToml);
+    };
+    push_formatted(deps, &path);
+
+    if !features.is_empty() {
+        if !doc.contains_key("dependency_features") {
+            doc.insert("dependency_features", table());
+        }
+
+        let Some(dependency_features) = doc["dependency_features"].as_table_mut() else {
+            return Err(FatalError::InvalidSporkToml);
+        };
+        dependency_features.insert(&name, value(features.iter().collect::<Array>()));
+    }
+
+    mkfile(SPORK_FILE_NAME, &doc.to_string())?;
+
+    if features.is_empty() {
+        success!("added dependency '{name}' at '{path}'");
+    } else {
+        success!(
+            "added dependency '{name}' at '{path}' with features {}",
+            features.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Removes a dependency by name (or path) from `dependencies`, every `[target]` table and
+/// `dependency_features`. Sources cloned into `deps/` are left in place.
+pub fn remove(name: &str) -> FatalResult<()> {
+    let mut doc = read_document()?;
+    let mut removed = Vec::new();
+
+    let mut remove_from = |deps: &mut Array| {
+        let indices: Vec<_> = deps
+            .iter()
+            .enumerate()
+            .filter_map(|(i, value)| {
+                let path = value.as_str()?;
+                let matches = path.trim_end_matches(['/', '\\'])
+                    == name.trim_end_matches(['/', '\\'])
+                    || dependency_name(path).as_deref() == Some(name);
+                matches.then(|| (i, path.to_string()))
+            })
+            .collect();
+
+        for (i, path) in indices.into_iter().rev() {
+            deps.remove(i);
+            removed.push(path);
+        }
+    };
+
+    if let Some(deps) = doc["project"]
+        .get_mut("dependencies")
+        .and_then(|deps| deps.as_array_mut())
+    {
+        remove_from(deps);
+    }
+
+    if let Some(tables) = doc
+        .get_mut("target")
+        .and_then(|tables| tables.as_table_mut())
+    {
+        for (_, table) in tables.iter_mut() {
+            if let Some(deps) = table
+                .get_mut("dependencies")
+                .and_then(|deps| deps.as_array_mut())
+            {
+                remove_from(deps);
+            }
+
+            if let Some(deps) = table
+                .get_mut("override")
+                .and_then(|table| table.get_mut("dependencies"))
+                .and_then(|deps| deps.as_array_mut())
+            {
+                remove_from(deps);
+            }
+        }
+    }
+
+    if removed.is_empty() {
+        return Err(FatalError::NoSuchDependency {
+            name: name.to_string(),
+        });
+    }
+
+    removed.sort();
+    removed.dedup();
+
+    if let Some(dependency_features) = doc
+        .get_mut("dependency_features")
+        .and_then(|features| features.as_table_mut())
+    {
+        dependency_features.remove(name);
+        for path in &removed {
+            if let Some(dep_name) = dependency_name(path) {
+                dependency_features.remove(&dep_name);
+            }
+        }
+
+        if dependency_features.is_empty() {
+            doc.remove("dependency_features");
+        }
+    }
+
+    mkfile(SPORK_FILE_NAME, &doc.to_string())?;
+
+    for path in &removed {
+        success!("removed dependency '{name}' at '{path}'");
+        if path.starts_with(&format!("{GIT_DEPS_DIR}/")) {
+            warning!("'{path}' was kept - delete it if nothing else uses it");
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the project at `path` can be used as a dependency of the project with the given
+/// features, returning its name.
+fn check_dependency(
+    path: &str,
+    spork_file: &ProjectFile,
+    features: &[String],
+) -> FatalResult<String> {
+    let dep_file = parse_spork_file(&format!("{path}/{SPORK_FILE_NAME}"))?;
+    dep_file.project.check_features(features)?;
+    let name = dep_file.project.name;
+
+    if dep_file.project.kind != ProjectType::library {
+        return Err(FatalError::NoExecutableDependencies { name });
+    }
+
+    for existing in spork_file.project.dependencies.iter().flatten() {
+        if dependency_name(existing).as_deref() == Some(name.as_str()) {
+            return Err(FatalError::DependencyExists { name });
+        }
+    }
+
+    let Some(dep_targets) = dep_file.project.targets else {
+        return Ok(name);
+    };
+
+    let dep_targets = dep_targets
+        .iter()
+        .map(|target| Target::new(target, false))
+        .collect::<FatalResult<Vec<_>>>()?;
+
+    match &spork_file.project.targets {
+        Some(targets) => {
+            for target in targets {
+                let target = Target::new(target, false)?;
+                if !dep_targets.contains(&target) {
+                    return Err(FatalError::NoTargetSupportDependency { dep: name, target });
+                }
+            }
+        }
+        None => {
+            let targets: Vec<_> = dep_targets
+                .iter()
+                .map(|target| target.to_string())
+                .collect();
+            warning!(
+                "'{name}' only supports targets {} - consider setting 'targets' for this project",
+                targets.join(", ")
+            );
+        }
+    }
+
+    Ok(name)
+}
+
+fn clone_dependency(url: &str) -> FatalResult<String> {
+    let repo_name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".git");
+    let path = format!("{GIT_DEPS_DIR}/{repo_name}");
+
+    if Path::new(&path).exists() {
+        return Err(FatalError::DependencyDirExists { path });
+    }
+
+    mkdir_all(GIT_DEPS_DIR)?;
+    progress!("cloning '{url}' into '{path}'...");
+
+    if let Err(err) = Repository::clone(url, &path) {
+        return Err(FatalError::FailedCloneDependency {
+            url: url.to_string(),
+            err,
+        });
+    }
+
+    Ok(path)
+}
+
+fn is_git_url(source: &str) -> bool {
+    if ["https://", "http://", "ssh://", "git://", "file://", "git@"]
+        .iter()
+        .any(|prefix| source.starts_with(prefix))
+    {
+        return true;
+    }
+
+    // scp-like URLs, e.g. 'host:group/repo.git' - a local directory named 'repo.git', or a
+    // Windows path such as 'C:\libs\repo.git', is a path
+    if !source.ends_with(".git") || Path::new(source).exists() {
+        return false;
+    }
+
+    source
+        .split_once(':')
+        .is_some_and(|(host, _)| host.len() > 1 && !host.contains(['/', '\\']))
+}
+
+/// The project name of the dependency at `path`, if it can be read.
+fn dependency_name(path: &str) -> Option<String> {
+    parse_spork_file(&format!("{path}/{SPORK_FILE_NAME}"))
+        .ok()
+        .map(|spork_file| spork_file.project.name)
+}
+
+/// Appends a value to an array, laid out like the values already in it.
+fn push_formatted(array: &mut Array, value: &str) {
+    let decor = array
+        .iter()
+        .last()
+        .map(|last| last.decor().prefix().cloned());
+
+    array.push(value);
+
+    if let Some(Some(prefix)) = decor {
+        if let Some(last) = array.iter_mut().last() {
+            last.decor_mut().set_prefix(prefix);
+        }
+    }
+}
+
+fn read_document() -> FatalResult<Document> {
+    let src = match fs::read_to_string(SPORK_FILE_NAME) {
+        Ok(res) => res,
+        Err(_) => {
+            return Err(FatalError::NoSporkToml {
+                path: SPORK_FILE_NAME.to_string(),
+            })
+        }
+    };
+
+    match src.parse::<Document>() {
+        Ok(res) => Ok(res),
+        Err(_) => Err(FatalError::InvalidSporkToml),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn git_urls_are_recognized() {
+        assert!(is_git_url("https://github.com/user/lib"));
+        assert!(is_git_url("git@github.com:user/lib.git"));
+        assert!(is_git_url("ssh://git@host/lib.git"));
+        assert!(is_git_url("host:group/lib.git"));
+
+        assert!(!is_git_url("../lib"));
+        assert!(!is_git_url("../lib.git"));
+        assert!(!is_git_url("libs/lib.git"));
+        assert!(!is_git_url(r"C:\libs\lib.git"));
+    }
+}
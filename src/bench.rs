@@ -0,0 +1,263 @@
+use std::{collections::BTreeMap, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    build::{build, output_cmd, ArtifactKind, BuildInfo, BuildOptions},
+    error::{FatalError, FatalResult},
+    message::is_json,
+    progress, success,
+    targets::Target,
+    util::{launch_dir, mkdir_all, mkfile},
+    warning,
+};
+
+pub const BENCH_HEADER_NAME: &str = "spork_bench.h";
+pub const BENCH_RUNNER_NAME: &str = "spork_bench.c";
+
+// Changes smaller than this are treated as noise
+const CHANGE_THRESHOLD: f64 = 0.05;
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct BenchStats {
+    mean: f64,
+    median: f64,
+    stddev: f64,
+}
+
+impl BenchStats {
+    fn new(samples: &[f64]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        let len = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / len;
+        let median = if sorted.len().is_multiple_of(2) {
+            (sorted[sorted.len() / 2 - 1] + sorted[sorted.len() / 2]) / 2.0
+        } else {
+            sorted[sorted.len() / 2]
+        };
+        let variance = if sorted.len() > 1 {
+            sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (len - 1.0)
+        } else {
+            0.0
+        };
+
+        Self {
+            mean,
+            median,
+            stddev: variance.sqrt(),
+        }
+    }
+}
+
+/// Writes the benchmark harness bundled with spork - a header for the benchmarks to include, and
+/// a runner linked into each of them. Returns the directory they were written to.
+pub fn write_harness(dir: &str) -> FatalResult<String> {
+    let harness_dir = format!("{dir}/spork_bench");
+    mkdir_all(&harness_dir)?;
+
+    mkfile(
+        &format!("{harness_dir}/{BENCH_HEADER_NAME}"),
+        include_str!("../template/spork_bench.h"),
+    )?;
+    mkfile(
+        &format!("{harness_dir}/{BENCH_RUNNER_NAME}"),
+        include_str!("../template/spork_bench.c"),
+    )?;
+
+    Ok(harness_dir)
+}
+
+/// Builds every benchmark in `benches` in release mode and runs it on the host, reporting how
+/// each one compares to the saved baseline. A baseline is only saved automatically when it doesn't
+/// exist yet - after that, only `--save-baseline` replaces it.
+pub fn bench(
+    filter: Option<&str>,
+    save_baseline: bool,
+    baseline_name: Option<&str>,
+) -> FatalResult<()> {
+    if let Some(name) = baseline_name {
+        if !is_valid_baseline_name(name) {
+            return Err(FatalError::InvalidBaselineName {
+                name: name.to_string(),
+            });
+        }
+    }
+
+    let infos = build(&BuildOptions {
+        release: true,
+        benches: true,
+        ..Default::default()
+    })?;
+
+    let host = Target::host()?;
+    let Some(info) = infos.iter().find(|info| info.target == host) else {
+        return Err(FatalError::NoSupportedTargets);
+    };
+
+    let baseline_path = baseline_path(info, baseline_name);
+    let baseline: BTreeMap<String, BenchStats> = match fs::read_to_string(&baseline_path) {
+        Ok(src) => toml::from_str(&src).unwrap_or_default(),
+        Err(_) => BTreeMap::new(),
+    };
+
+    let mut results = BTreeMap::new();
+
+    for bench in info
+        .outputs
+        .iter()
+        .filter(|artifact| artifact.kind == ArtifactKind::Bench)
+    {
+        progress!("running benchmark '{}'...", bench.name);
+
+        let mut cmd = output_cmd(&bench.path, info);
+        if let Some(filter) = filter {
+            cmd.arg(filter);
+        }
+
+        let output = match cmd.output() {
+            Ok(res) => res,
+            Err(err) => {
+                return Err(FatalError::FailedRunOutput {
+                    path: bench.path.clone(),
+                    err,
+                })
+            }
+        };
+
+        if !output.status.success() {
+            return Err(FatalError::BenchFailed {
+                name: bench.name.clone(),
+            });
+        }
+
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let Some(line) = line.strip_prefix("spork-bench ") else {
+                if is_json() {
+                    eprintln!("{line}");
+                } else {
+                    println!("{line}");
+                }
+                continue;
+            };
+
+            let mut comps = line.split_whitespace();
+            let Some(name) = comps.next() else {
+                continue;
+            };
+            let samples: Vec<f64> = comps.filter_map(|sample| sample.parse().ok()).collect();
+            if samples.is_empty() {
+                continue;
+            }
+
+            let name = format!("{}/{name}", bench.name);
+            let stats = BenchStats::new(&samples);
+            report(&name, &stats, baseline.get(&name));
+            results.insert(name, stats);
+        }
+    }
+
+    if results.is_empty() {
+        warning!("no benchmarks were run");
+        return Ok(());
+    }
+
+    if save_baseline || baseline.is_empty() {
+        // Results of benchmarks that weren't run are kept
+        let mut new_baseline = if save_baseline {
+            baseline
+        } else {
+            BTreeMap::new()
+        };
+        new_baseline.extend(results);
+
+        mkfile(
+            &baseline_path,
+            &toml::to_string_pretty(&new_baseline).unwrap(),
+        )?;
+        success!("saved baseline to '{baseline_path}'");
+    }
+
+    Ok(())
+}
+
+fn report(name: &str, stats: &BenchStats, baseline: Option<&BenchStats>) {
+    let summary = format!(
+        "{name}: median {}, mean {} ± {}",
+        format_ns(stats.median),
+        format_ns(stats.mean),
+        format_ns(stats.stddev)
+    );
+
+    let Some(baseline) = baseline else {
+        success!("{summary}");
+        return;
+    };
+
+    let change = (stats.median - baseline.median) / baseline.median;
+    let change_text = format!("{:+.1}% vs baseline", change * 100.0);
+
+    if change > CHANGE_THRESHOLD {
+        warning!("{summary} ({change_text} - regressed)");
+    } else if change < -CHANGE_THRESHOLD {
+        success!("{summary} ({change_text} - improved)");
+    } else {
+        success!("{summary} ({change_text} - no change)");
+    }
+}
+
+fn format_ns(ns: f64) -> String {
+    if ns >= 1e9 {
+        format!("{:.3} s", ns / 1e9)
+    } else if ns >= 1e6 {
+        format!("{:.3} ms", ns / 1e6)
+    } else if ns >= 1e3 {
+        format!("{:.3} µs", ns / 1e3)
+    } else {
+        format!("{ns:.3} ns")
+    }
+}
+
+fn is_valid_baseline_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn baseline_path(info: &BuildInfo, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!(
+            "{}/bin/{}/bench-baseline-{name}.toml",
+            launch_dir(),
+            info.target
+        ),
+        None => format!("{}/bin/{}/bench-baseline.toml", launch_dir(), info.target),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_use_the_sample_median() {
+        let stats = BenchStats::new(&[4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(stats.median, 2.5);
+        assert_eq!(stats.mean, 2.5);
+
+        let stats = BenchStats::new(&[5.0]);
+        assert_eq!(stats.median, 5.0);
+        assert_eq!(stats.stddev, 0.0);
+    }
+
+    #[test]
+    fn baseline_names_are_file_name_safe() {
+        assert!(is_valid_baseline_name("main"));
+        assert!(is_valid_baseline_name("before-simd_2"));
+        assert!(!is_valid_baseline_name(""));
+        assert!(!is_valid_baseline_name("../main"));
+        assert!(!is_valid_baseline_name("a b"));
+    }
+}
@@ -1,57 +1,160 @@
 use std::{
-    collections::{hash_map::IntoIter, HashMap},
+    collections::{hash_map::IntoIter, BTreeMap, HashMap},
     env,
     fmt::Display,
+    fs,
+    path::{Component, Path, PathBuf},
     process::Command,
     time::Instant,
 };
 
-use regex::Regex;
+use semver::Version;
+use serde::Serialize;
 
 use crate::{
+    bench::{write_harness, BENCH_RUNNER_NAME},
+    build_info::{clear_git_states, generate_build_info},
+    build_script::run_build_script,
     error::{FatalError, FatalResult},
+    export::{export_building_define, export_include_dir, generate_export_header},
+    hooks::run_hooks,
+    message::{emit, emit_diagnostics, is_json, redirect_stdout, Message},
     progress,
-    project::{parse_spork_file, ProjectFile, ProjectType},
+    project::{parse_spork_file, BinInfo, Hooks, Metadata, ProjectFile, ProjectType, TargetConfig},
+    resources::{generate_resources, RESOURCES_SOURCE_NAME},
+    sanitize::Sanitizer,
+    sources::{collect_bins, collect_extra_bins, collect_sources},
     success,
     targets::{OperatingSystem, Target},
-    util::{mkdir_all, walkdir, LAUNCH_DIR},
+    toolchain::{set_toolchain, toolchain},
+    util::{launch_dir, mkdir_all, run_tool, symlink},
+    version_info::generate_version_resource,
     warning, SPORK_FILE_NAME,
 };
 
+#[derive(Default, Clone)]
+pub struct BuildOptions {
+    pub release: bool,
+    pub all: bool,
+    pub examples: bool,
+    pub tests: bool,
+    pub benches: bool,
+    pub sanitize: Option<Vec<String>>,
+    pub coverage: bool,
+    pub check: bool,
+}
+
 pub struct BuildInfo {
     pub name: String,
+    pub metadata: Metadata,
     pub release: bool,
+    pub sanitizers: Vec<Sanitizer>,
+    pub coverage: bool,
+    pub check: bool,
     pub kind: ProjectType,
     pub target: Target,
-    pub output_path: Option<String>,
+    pub config: TargetConfig,
+    pub hooks: Hooks,
+    pub resources: BTreeMap<String, String>,
+    pub bins: Vec<BinInfo>,
+    pub objects: Vec<String>,
+    pub outputs: Vec<Artifact>,
     pub dependencies: Option<Dependencies>,
 }
 
-impl Display for BuildInfo {
+pub struct Artifact {
+    pub name: String,
+    pub kind: ArtifactKind,
+    pub path: String,
+}
+
+#[derive(PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    Executable,
+    Library,
+    Example,
+    Test,
+    Bench,
+}
+
+impl Display for ArtifactKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let suffix = format!(", {}", self.target);
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Executable => "executables",
+                Self::Library => "libraries",
+                Self::Example => "examples",
+                Self::Test => "tests",
+                Self::Bench => "benchmarks",
+            }
+        )
+    }
+}
 
+impl BuildInfo {
+    pub fn profile(&self) -> &'static str {
         if self.release {
-            write!(f, "{}, release{}", self.kind, suffix)?;
+            "release"
         } else {
-            write!(f, "{}, debug{}", self.kind, suffix)?;
+            "debug"
+        }
+    }
+}
+
+impl Display for BuildInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, {}, {}", self.kind, self.profile(), self.target)?;
+
+        if let Some(sanitize) = Sanitizer::cc_arg(&self.sanitizers) {
+            write!(f, ", {}", &sanitize[2..])?;
         }
 
         Ok(())
     }
 }
 
-pub fn build(release: bool, all: bool) -> FatalResult<Vec<BuildInfo>> {
+pub fn build(options: &BuildOptions) -> FatalResult<Vec<BuildInfo>> {
     let spork_file = parse_spork_file(SPORK_FILE_NAME)?;
-    build_project(spork_file, release, all)
+    spork_file.warn_invalid_metadata();
+    set_toolchain(spork_file.toolchain.as_ref());
+    toolchain().check_zig_version()?;
+    clear_git_states();
+    build_project(spork_file, options)
 }
 
-pub fn build_and_run(release: bool, all: bool) -> FatalResult<()> {
-    let infos = build(release, all)?;
-    let mut has_run = false;
+pub fn build_and_run(
+    options: &BuildOptions,
+    bin: Option<String>,
+    example: Option<String>,
+) -> FatalResult<()> {
+    let mut cmd = build_for_run(options, bin, example)?;
+
+    if let Err(err) = cmd.status() {
+        return Err(FatalError::FailedRunOutput {
+            path: cmd.get_program().to_string_lossy().to_string(),
+            err,
+        });
+    }
+
+    Ok(())
+}
+
+/// Builds the project and returns the command running its executable on the host.
+pub fn build_for_run(
+    options: &BuildOptions,
+    bin: Option<String>,
+    example: Option<String>,
+) -> FatalResult<Command> {
+    let infos = build(&BuildOptions {
+        examples: example.is_some(),
+        ..options.clone()
+    })?;
 
     for info in infos {
-        if info.kind == ProjectType::library {
+        if info.kind == ProjectType::library && example.is_none() {
             return Err(FatalError::CannotRunLib);
         }
 
@@ -60,87 +163,159 @@ pub fn build_and_run(release: bool, all: bool) -> FatalResult<()> {
             continue;
         }
 
-        let output_to_run = info.output_path.unwrap();
-        if let Err(err) = Command::new(&output_to_run).status() {
-            return Err(FatalError::FailedRunOutput {
-                path: output_to_run,
-                err,
-            });
-        }
-        has_run = true;
+        let path = match &example {
+            Some(example) => match info.outputs.iter().find(|artifact| {
+                artifact.kind == ArtifactKind::Example && &artifact.name == example
+            }) {
+                Some(artifact) => artifact.path.clone(),
+                None => {
+                    return Err(FatalError::NoSuchExample {
+                        name: example.clone(),
+                    })
+                }
+            },
+            None => select_output(&info, bin.as_deref())?,
+        };
+
+        let mut cmd = output_cmd(&path, &info);
+        redirect_stdout(&mut cmd);
+        return Ok(cmd);
     }
 
-    if has_run {
-        Ok(())
-    } else {
-        Err(FatalError::NoSupportedTargets)
+    Err(FatalError::NoSupportedTargets)
+}
+
+/// A command running an executable built for the host, with the build's shared libraries on the
+/// library search path - for executables that were linked without rpaths, or moved since.
+pub fn output_cmd(path: &str, info: &BuildInfo) -> Command {
+    let mut cmd = Command::new(path);
+
+    let var = match info.target.os {
+        OperatingSystem::Windows => "PATH",
+        _ => "LD_LIBRARY_PATH",
+    };
+
+    let mut paths = vec![out_dir(info).into()];
+    if let Some(existing) = env::var_os(var) {
+        paths.extend(env::split_paths(&existing));
+    }
+
+    if let Ok(joined) = env::join_paths(paths) {
+        cmd.env(var, joined);
+    }
+
+    cmd
+}
+
+fn select_output(info: &BuildInfo, bin: Option<&str>) -> FatalResult<String> {
+    let executables: Vec<_> = info
+        .outputs
+        .iter()
+        .filter(|artifact| artifact.kind == ArtifactKind::Executable)
+        .collect();
+
+    let artifact = match bin {
+        Some(bin) => executables.iter().find(|artifact| artifact.name == bin),
+        None if executables.len() == 1 => executables.first(),
+        None => executables
+            .iter()
+            .find(|artifact| artifact.name == info.name),
+    };
+
+    match artifact {
+        Some(artifact) => Ok(artifact.path.clone()),
+        None => match bin {
+            Some(bin) => Err(FatalError::NoSuchBin {
+                name: bin.to_string(),
+            }),
+            None => Err(FatalError::AmbiguousBin {
+                names: executables
+                    .iter()
+                    .map(|artifact| artifact.name.clone())
+                    .collect(),
+            }),
+        },
     }
 }
 
-fn build_project(spork_file: ProjectFile, release: bool, all: bool) -> FatalResult<Vec<BuildInfo>> {
+fn build_project(spork_file: ProjectFile, options: &BuildOptions) -> FatalResult<Vec<BuildInfo>> {
     let mut build_infos = Vec::new();
-    if let Some(targets) = spork_file.project.targets {
-        if targets.is_empty() {
-            warning!("no targets specified - nothing will be built");
-            return Ok(build_infos);
-        }
-
-        if all {
-            for target in targets {
-                let target = Target::new(&target, false)?;
-                let dependencies = match &spork_file.project.dependencies {
-                    Some(deps) => Some(Dependencies::new(deps.clone(), &target)?),
-                    None => None,
-                };
-
-                let mut info = BuildInfo {
-                    name: spork_file.project.name.clone(),
-                    release,
-                    kind: spork_file.project.kind,
-                    target,
-                    output_path: None,
-                    dependencies,
-                };
 
-                build_target(&mut info)?;
-                build_infos.push(info);
+    let targets = match &spork_file.project.targets {
+        Some(targets) => {
+            if targets.is_empty() {
+                warning!("no targets specified - nothing will be built");
+                return Ok(build_infos);
             }
-        } else {
-            let target = Target::new(&targets[0], false)?;
-            let dependencies = match spork_file.project.dependencies {
-                Some(deps) => Some(Dependencies::new(deps, &target)?),
-                None => None,
-            };
 
-            let mut info = BuildInfo {
-                name: spork_file.project.name.clone(),
-                release,
-                kind: spork_file.project.kind,
-                target,
-                output_path: None,
-                dependencies,
+            let targets = if options.all {
+                &targets[..]
+            } else {
+                &targets[..1]
             };
-
-            build_target(&mut info)?;
-            build_infos.push(info);
+            targets
+                .iter()
+                .map(|target| Target::new(target, false))
+                .collect::<FatalResult<Vec<_>>>()?
         }
-    } else {
-        let target = Target::host()?;
-        let dependencies = match spork_file.project.dependencies {
-            Some(deps) => Some(Dependencies::new(deps, &target)?),
+        None => vec![Target::host()?],
+    };
+
+    let sanitize = match &options.sanitize {
+        Some(sanitize) => sanitize.clone(),
+        None => spork_file
+            .profile(options.release)
+            .and_then(|profile| profile.sanitize.clone())
+            .unwrap_or_default(),
+    };
+
+    for target in targets {
+        let sanitizers = Sanitizer::parse_list(&sanitize, &target)?;
+        let bins = match spork_file.project.kind {
+            ProjectType::executable => collect_bins(&spork_file, &target)?,
+            ProjectType::library => Vec::new(),
+        };
+        let config = spork_file.target_config(&target)?;
+        let dependencies = match config.dependencies.clone() {
+            Some(deps) => Some(Dependencies::new(
+                deps,
+                &target,
+                spork_file.dependency_features.as_ref(),
+            )?),
             None => None,
         };
 
         let mut info = BuildInfo {
-            name: spork_file.project.name,
-            release,
+            name: spork_file.project.name.clone(),
+            metadata: spork_file.project.metadata(),
+            release: options.release,
+            sanitizers,
+            coverage: options.coverage,
+            check: options.check,
             kind: spork_file.project.kind,
             target,
-            output_path: None,
+            config,
+            hooks: spork_file.hooks.clone().unwrap_or_default(),
+            resources: spork_file.resources.clone().unwrap_or_default(),
+            bins,
+            objects: Vec::new(),
+            outputs: Vec::new(),
             dependencies,
         };
 
         build_target(&mut info)?;
+        if options.examples {
+            build_extra_bins(&mut info, "examples", ArtifactKind::Example)?;
+        }
+
+        if options.tests {
+            build_extra_bins(&mut info, "tests", ArtifactKind::Test)?;
+        }
+
+        if options.benches {
+            build_extra_bins(&mut info, "benches", ArtifactKind::Bench)?;
+        }
+
         build_infos.push(info);
     }
 
@@ -161,10 +336,19 @@ fn build_target(info: &mut BuildInfo) -> FatalResult<()> {
 
             build_target(&mut BuildInfo {
                 name: dep.name,
+                metadata: dep.metadata,
                 release: info.release,
+                sanitizers: info.sanitizers.clone(),
+                coverage: info.coverage,
+                check: info.check,
                 kind: ProjectType::library,
                 target: info.target.clone(),
-                output_path: info.output_path.clone(),
+                config: dep.config,
+                hooks: dep.hooks,
+                resources: dep.resources,
+                bins: Vec::new(),
+                objects: Vec::new(),
+                outputs: Vec::new(),
                 dependencies: dep.deps,
             })?;
 
@@ -179,130 +363,433 @@ fn build_target(info: &mut BuildInfo) -> FatalResult<()> {
 
     let start_time = Instant::now();
 
-    let obj_name_regex = Regex::new(r"[/\\]").unwrap();
-
     let out_dir = out_dir(info);
-    progress!("building '{}'...", info.name);
+    if is_json() {
+        emit(&Message::BuildStarted {
+            name: &info.name,
+            target: info.target.to_string(),
+            profile: info.profile(),
+        });
+    } else {
+        progress!("building '{}'...", info.name);
+    }
 
     mkdir_all(&format!("{out_dir}/obj"))?;
 
+    if let Some(pre_build) = &info.hooks.pre_build {
+        run_hooks(pre_build, info)?;
+    }
+
+    if let Some(output) = run_build_script(info)? {
+        let config = &mut info.config;
+        config
+            .flags
+            .get_or_insert_with(Vec::new)
+            .extend(output.cflags);
+        config
+            .defines
+            .get_or_insert_with(Vec::new)
+            .extend(output.defines);
+        config
+            .sources
+            .get_or_insert_with(Vec::new)
+            .extend(output.sources);
+    }
+
+    if info.kind == ProjectType::library {
+        generate_export_header(info)?;
+    }
+
+    let build_info_dir = generate_build_info(info)?;
+    info.config
+        .flags
+        .get_or_insert_with(Vec::new)
+        .push(format!("-I{build_info_dir}"));
+
+    if let Some(resources_dir) = generate_resources(info)? {
+        let config = &mut info.config;
+        config
+            .flags
+            .get_or_insert_with(Vec::new)
+            .push(format!("-I{resources_dir}"));
+        config
+            .sources
+            .get_or_insert_with(Vec::new)
+            .push(format!("{resources_dir}/{RESOURCES_SOURCE_NAME}"));
+    }
+
     let mut objects = Vec::new();
     let mut had_error = false;
 
-    for file in walkdir("src")? {
-        if !(file.ends_with(".c") || file.ends_with(".h")) {
-            warning!("spork can only compile C files (*.c, *.h) - consider removing file '{file}'");
+    for file in collect_sources(&info.config, &info.target)? {
+        // Executable roots are compiled separately below
+        if is_bin_source(&file, &info.bins) {
             continue;
         }
 
-        if file.ends_with(".h") {
-            // TODO : proper handling of h files
-            continue;
+        let obj_path = obj_path(&out_dir, &file);
+        if !build_obj(&file, &obj_path, info)? {
+            had_error = true;
         }
 
-        let obj_name = obj_name_regex.replace_all(&file[4..(file.len() - 2)], "-");
+        objects.push(obj_path);
+    }
 
-        let obj_path = format!("{out_dir}/obj/{}.o", obj_name);
-        let build_obj_result = build_obj(&file, &obj_path, info)?;
-        if !build_obj_result {
-            had_error = true;
+    let mut outputs = Vec::new();
+
+    if info.bins.is_empty() {
+        if objects.is_empty() {
+            return Err(FatalError::NoSourceFiles);
         }
 
-        objects.push(obj_path);
+        let main_obj_path = obj_path(&out_dir, "src/main.c");
+        info.objects = objects
+            .iter()
+            .filter(|obj_path| obj_path != &&main_obj_path)
+            .cloned()
+            .collect();
+
+        outputs.push((info.name.clone(), objects));
+    } else {
+        info.objects = objects.clone();
+
+        for bin in &info.bins {
+            let obj_path = obj_path(&out_dir, &bin.path);
+            if !build_obj(&bin.path, &obj_path, info)? {
+                had_error = true;
+            }
+
+            let mut bin_objects = objects.clone();
+            bin_objects.push(obj_path);
+            outputs.push((bin.name.clone(), bin_objects));
+        }
     }
 
-    if objects.is_empty() {
-        return Err(FatalError::NoSourceFiles);
+    if had_error {
+        return Err(FatalError::CompilationFailed);
     }
 
-    let output_path = match info.kind {
-        ProjectType::executable => {
-            if info.target.os == OperatingSystem::Windows {
-                format!("{out_dir}/{}.exe", info.name)
-            } else {
-                format!("{out_dir}/{}", info.name)
-            }
+    // Checking stops once everything compiles
+    if info.check {
+        success!("checked in {:.2?} ({info})", Instant::now() - start_time);
+        return Ok(());
+    }
+
+    for (name, objects) in outputs {
+        let output_path = output_path(&out_dir, &name, info.kind, &info.target);
+
+        if !build_output(objects, &output_path, info)? {
+            return Err(FatalError::LinkFailed);
         }
-        ProjectType::library => {
-            if info.target.os == OperatingSystem::Windows {
-                format!("{out_dir}/{}.dll", info.name)
-            } else {
-                format!("{out_dir}/{}.so", info.name)
+
+        add_output(
+            info,
+            Artifact {
+                name,
+                kind: match info.kind {
+                    ProjectType::executable => ArtifactKind::Executable,
+                    ProjectType::library => ArtifactKind::Library,
+                },
+                path: output_path,
+            },
+        );
+    }
+
+    if info.kind == ProjectType::library {
+        build_static_lib(info)?;
+    }
+
+    if let Some(post_build) = &info.hooks.post_build {
+        run_hooks(post_build, info)?;
+    }
+
+    let end_time = Instant::now();
+    if is_json() {
+        emit(&Message::BuildFinished {
+            name: &info.name,
+            target: info.target.to_string(),
+            profile: info.profile(),
+            duration: (end_time - start_time).as_secs_f64(),
+        });
+    } else {
+        success!("finished in {:.2?} ({info})", end_time - start_time);
+    }
+
+    Ok(())
+}
+
+/// Builds the standalone executables in `dir` (examples or tests), which use the project like
+/// a dependency.
+fn build_extra_bins(info: &mut BuildInfo, dir: &str, kind: ArtifactKind) -> FatalResult<()> {
+    let extra_bins = collect_extra_bins(dir, &info.target)?;
+    if extra_bins.is_empty() {
+        warning!("no {kind} found in '{dir}'");
+        return Ok(());
+    }
+
+    let start_time = Instant::now();
+    progress!("building {kind} of '{}'...", info.name);
+
+    // Libraries link against themselves like any other dependency
+    let mut dependencies = info.dependencies.clone().unwrap_or_default();
+    if info.kind == ProjectType::library {
+        dependencies.path_to_deps.insert(
+            String::from("."),
+            Dependency {
+                name: info.name.clone(),
+                metadata: Metadata::default(),
+                kind: info.kind,
+                path: String::from("."),
+                targets: None,
+                config: TargetConfig::default(),
+                hooks: Hooks::default(),
+                resources: BTreeMap::new(),
+                deps: None,
+            },
+        );
+    }
+
+    let extra_dir = format!("{}/{dir}", out_dir(info));
+    mkdir_all(&format!("{extra_dir}/obj"))?;
+
+    // Benchmarks are built with the harness bundled with spork
+    let mut config = info.config.clone();
+    let mut harness_sources = Vec::new();
+    if kind == ArtifactKind::Bench {
+        let harness_dir = write_harness(&extra_dir)?;
+        config
+            .flags
+            .get_or_insert_with(Vec::new)
+            .push(format!("-I{harness_dir}"));
+        harness_sources.push(format!("{harness_dir}/{BENCH_RUNNER_NAME}"));
+    }
+
+    for extra_bin in extra_bins {
+        let extra_info = BuildInfo {
+            name: extra_bin.name.clone(),
+            metadata: Metadata::default(),
+            release: info.release,
+            sanitizers: info.sanitizers.clone(),
+            coverage: info.coverage,
+            check: info.check,
+            kind: ProjectType::executable,
+            target: info.target.clone(),
+            config: config.clone(),
+            hooks: Hooks::default(),
+            resources: BTreeMap::new(),
+            bins: Vec::new(),
+            objects: Vec::new(),
+            outputs: Vec::new(),
+            dependencies: Some(dependencies.clone()),
+        };
+
+        let mut objects = Vec::new();
+        let mut had_error = false;
+
+        for file in extra_bin.sources.iter().chain(&harness_sources) {
+            let obj_path = obj_path(&extra_dir, file);
+            if !build_obj(file, &obj_path, &extra_info)? {
+                had_error = true;
             }
+
+            objects.push(obj_path);
         }
-    };
 
-    if !had_error {
-        let build_output_res = build_output(objects, &output_path, info)?;
+        if had_error {
+            return Err(FatalError::CompilationFailed);
+        }
 
-        if build_output_res {
-            let end_time = Instant::now();
-            success!("finished in {:.2?} ({info})", end_time - start_time);
-        } else {
+        // Tests and benchmarks of executables can't link against them, so they use their
+        // objects directly
+        if matches!(kind, ArtifactKind::Test | ArtifactKind::Bench)
+            && info.kind == ProjectType::executable
+        {
+            objects.extend(info.objects.iter().cloned());
+        }
+
+        let output_path = output_path(
+            &extra_dir,
+            &extra_bin.name,
+            ProjectType::executable,
+            &info.target,
+        );
+
+        if !build_output(objects, &output_path, &extra_info)? {
             return Err(FatalError::LinkFailed);
         }
-    } else {
-        return Err(FatalError::CompilationFailed);
+
+        // Windows only looks for DLLs next to the executable (or in PATH)
+        if info.target.os == OperatingSystem::Windows {
+            copy_dlls(info, &dependencies, &extra_dir)?;
+        }
+
+        add_output(
+            info,
+            Artifact {
+                name: extra_bin.name,
+                kind,
+                path: output_path,
+            },
+        );
     }
 
-    info.output_path = Some(output_path);
+    let end_time = Instant::now();
+    success!("finished {kind} in {:.2?} ({info})", end_time - start_time);
 
     Ok(())
 }
 
-fn build_obj(src_path: &str, obj_path: &str, info: &BuildInfo) -> FatalResult<bool> {
-    let mut cmd = common_build_cmd(info);
-    cmd.args(["-c", src_path, "-o", obj_path, "-Isrc"]);
-    cmd.args(info.target.cc_args());
+fn copy_dlls(info: &BuildInfo, dependencies: &Dependencies, dir: &str) -> FatalResult<()> {
+    let lib_dir = out_dir(info);
 
-    if info.kind == ProjectType::library {
-        cmd.args(["-Iinclude", "-DSPORK_EXPORT"]);
-    } else if let Some(deps) = info.dependencies.clone() {
-        for (_, dep) in deps {
-            cmd.arg(format!("-I../{}/include", dep.name));
+    for name in dependencies.library_names() {
+        let src_path = output_path(&lib_dir, &name, ProjectType::library, &info.target);
+        let dst_path = output_path(dir, &name, ProjectType::library, &info.target);
+
+        if let Err(err) = fs::copy(&src_path, &dst_path) {
+            return Err(FatalError::CannotCopyFile {
+                path: dst_path,
+                err,
+            });
         }
     }
 
-    if info.release {
-        cmd.args(["-O3", "-s"]);
-    } else {
-        cmd.args(["-O0", "-g", "-DSPORK_DEBUG"]);
+    Ok(())
+}
+
+/// Bundles a library's objects into a static library, alongside the shared one.
+fn build_static_lib(info: &BuildInfo) -> FatalResult<()> {
+    let static_lib_path = static_lib_path(&out_dir(info), &info.name);
+    mkdir_all(&format!("{}/static", out_dir(info)))?;
+
+    // Archiving into an existing library would keep the objects of removed sources
+    let _ = fs::remove_file(&static_lib_path);
+
+    let toolchain = toolchain();
+    let mut cmd = toolchain.archive_cmd(&static_lib_path, &info.objects);
+    run_tool(&mut cmd, &toolchain.ar_name())
+}
+
+fn add_output(info: &mut BuildInfo, artifact: Artifact) {
+    if is_json() {
+        emit(&Message::Artifact {
+            name: &artifact.name,
+            kind: artifact.kind,
+            path: &artifact.path,
+        });
     }
 
-    let cmd_output = match cmd.status() {
-        Ok(res) => res,
-        Err(err) => return Err(FatalError::FailedRunZigcc { err }),
-    };
+    info.outputs.push(artifact);
+}
 
-    if cmd_output.success() {
-        Ok(true)
-    } else {
-        Ok(false)
+pub fn output_path(out_dir: &str, name: &str, kind: ProjectType, target: &Target) -> String {
+    match kind {
+        ProjectType::executable => {
+            if target.os == OperatingSystem::Windows {
+                format!("{out_dir}/{name}.exe")
+            } else {
+                format!("{out_dir}/{name}")
+            }
+        }
+        ProjectType::library => {
+            if target.os == OperatingSystem::Windows {
+                format!("{out_dir}/{name}.dll")
+            } else {
+                format!("{out_dir}/lib{name}.so")
+            }
+        }
+    }
+}
+
+/// Static libraries are kept in their own directory, so that linking with `-l` picks the shared
+/// library.
+pub fn static_lib_path(out_dir: &str, name: &str) -> String {
+    format!("{out_dir}/static/lib{name}.a")
+}
+
+/// Mirrors the source's path under `obj`, so that no two sources share an object. Components
+/// that would leave `obj` (`..` and roots) become `@` names, and `@` in other names is doubled.
+fn obj_path(out_dir: &str, src_path: &str) -> String {
+    let file_stem = &src_path[..(src_path.len() - 2)];
+    let mut obj_path = PathBuf::from(format!("{out_dir}/obj"));
+
+    for component in Path::new(file_stem).components() {
+        match component {
+            Component::Normal(name) => obj_path.push(name.to_string_lossy().replace('@', "@@")),
+            Component::ParentDir => obj_path.push("@up"),
+            Component::RootDir => obj_path.push("@root"),
+            Component::Prefix(prefix) => obj_path.push(format!(
+                "@{}",
+                prefix
+                    .as_os_str()
+                    .to_string_lossy()
+                    .replace(|c: char| !c.is_alphanumeric(), "")
+            )),
+            Component::CurDir => {}
+        }
+    }
+
+    format!("{}.o", obj_path.to_string_lossy())
+}
+
+/// Whether a source is the root of one of the executables, rather than shared between them.
+fn is_bin_source(src_path: &str, bins: &[BinInfo]) -> bool {
+    let src_path = path_components(src_path);
+    bins.iter()
+        .any(|bin| path_components(&bin.path) == src_path)
+}
+
+/// The components of a path without `.`, so that `./src/main.c` equals `src/main.c`.
+fn path_components(path: &str) -> Vec<Component<'_>> {
+    Path::new(path)
+        .components()
+        .filter(|component| component != &Component::CurDir)
+        .collect()
+}
+
+fn build_obj(src_path: &str, obj_path: &str, info: &BuildInfo) -> FatalResult<bool> {
+    if let Some(dir) = Path::new(obj_path).parent() {
+        mkdir_all(&dir.to_string_lossy())?;
     }
+
+    let mut cmd = toolchain().cc_cmd();
+    cmd.args(["-c", src_path, "-o", obj_path]);
+    cmd.args(compile_args(info)?);
+
+    run_cc(&mut cmd)
 }
 
 fn build_output(objects: Vec<String>, output_path: &str, info: &BuildInfo) -> FatalResult<bool> {
-    let mut cmd = common_build_cmd(info);
+    let mut cmd = common_build_cmd(info)?;
     cmd.args([&format!("-L{}", out_dir(info))]);
     cmd.args(objects);
 
+    // Versioned libraries on Linux are linked to `lib<name>.so.MAJOR.MINOR.PATCH`, and
+    // `output_path` becomes a symlink to it
+    let version = lib_version(info);
+    let version_parts = version
+        .as_ref()
+        .map(|version| [version.major, version.minor, version.patch]);
+    let link_path = match version_parts {
+        Some([major, minor, patch]) if info.target.os == OperatingSystem::Linux => {
+            format!("{output_path}.{major}.{minor}.{patch}")
+        }
+        _ => output_path.to_string(),
+    };
+
     if info.kind == ProjectType::library {
-        cmd.args(["-shared"]);
+        cmd.args(toolchain().shared_lib_args(output_path, &info.target, version_parts));
+        cmd.args(["-o", &link_path]);
 
-        if info.target.os == OperatingSystem::Windows {
-            let import_lib_path = output_path.replace(".dll", ".lib");
-            cmd.arg(&format!("-Wl,--out-implib,{}", import_lib_path));
-            cmd.args(["-o", output_path]);
-        } else {
-            cmd.args([
-                "-o",
-                &output_path.replace(&info.name, &format!("lib{}", info.name)),
-            ]);
+        if let (Some(version), OperatingSystem::Windows) = (&version, &info.target.os) {
+            if let Some(resource_path) = generate_version_resource(info, version)? {
+                cmd.arg(resource_path);
+            }
         }
     } else {
         if let Some(deps) = info.dependencies.clone() {
-            if info.target.os != OperatingSystem::Windows {
-                cmd.arg(format!("-Wl,-rpath,{}", "."));
+            if info.target.os == OperatingSystem::Linux {
+                cmd.args(rpath_args(output_path, &out_dir(info)));
             }
 
             for (_, dep) in deps {
@@ -313,60 +800,267 @@ fn build_output(objects: Vec<String>, output_path: &str, info: &BuildInfo) -> Fa
         cmd.args(["-o", output_path]);
     }
 
+    if let Some(link_flags) = &info.config.link_flags {
+        cmd.args(link_flags);
+    }
+
     if info.release {
         cmd.args(["-O3", "-s"]);
     } else {
         cmd.args(["-O0", "-g"]);
     }
 
-    let cmd_output = match cmd.status() {
+    if !run_cc(&mut cmd)? {
+        return Ok(false);
+    }
+
+    if let (Some([major, ..]), true) = (version_parts, link_path != output_path) {
+        // lib<name>.so -> lib<name>.so.MAJOR -> lib<name>.so.MAJOR.MINOR.PATCH
+        let soname_path = format!("{output_path}.{major}");
+        symlink(&file_name(&link_path), &soname_path)?;
+        symlink(&file_name(&soname_path), output_path)?;
+    }
+
+    Ok(true)
+}
+
+/// The `MAJOR.MINOR.PATCH` version of a library, which versions its shared library - libraries
+/// without a valid semantic version (which only get a warning) are unversioned.
+fn lib_version(info: &BuildInfo) -> Option<Version> {
+    if info.kind != ProjectType::library {
+        return None;
+    }
+
+    Version::parse(info.metadata.version.as_deref()?).ok()
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Rpaths relative to the executable (`$ORIGIN`), so that it finds its shared libraries wherever
+/// it's run from - both in the build directory and when installed into `bin` next to `lib`.
+fn rpath_args(output_path: &str, lib_dir: &str) -> [String; 2] {
+    let depth = Path::new(output_path)
+        .parent()
+        .and_then(|dir| dir.strip_prefix(lib_dir).ok())
+        .map_or(0, |rel| rel.components().count());
+
+    let mut origin = String::from("$ORIGIN");
+    for _ in 0..depth {
+        origin.push_str("/..");
+    }
+
+    [
+        format!("-Wl,-rpath,{origin}"),
+        String::from("-Wl,-rpath,$ORIGIN/../lib"),
+    ]
+}
+
+/// Runs the compiler, returning whether it succeeded. Its diagnostics are emitted as messages
+/// with `--message-format json`, and printed as they are otherwise.
+pub fn run_cc(cmd: &mut Command) -> FatalResult<bool> {
+    if !is_json() {
+        return match cmd.status() {
+            Ok(status) => Ok(status.success()),
+            Err(err) => Err(FatalError::FailedRunCompiler {
+                cc: toolchain().cc_name(),
+                err,
+            }),
+        };
+    }
+
+    let output = match cmd.output() {
         Ok(res) => res,
-        Err(err) => return Err(FatalError::FailedRunZigcc { err }),
+        Err(err) => {
+            return Err(FatalError::FailedRunCompiler {
+                cc: toolchain().cc_name(),
+                err,
+            })
+        }
     };
 
-    if cmd_output.success() {
-        Ok(true)
+    emit_diagnostics(&String::from_utf8_lossy(&output.stderr));
+    Ok(output.status.success())
+}
+
+/// The flags every translation unit of a build is compiled with - everything but the source and
+/// object paths.
+pub fn compile_args(info: &BuildInfo) -> FatalResult<Vec<String>> {
+    let mut args = common_args(info)?;
+    args.push(String::from("-Isrc"));
+    args.extend(info.target.cc_args());
+
+    if let Some(defines) = &info.config.defines {
+        args.extend(defines.iter().map(|define| format!("-D{define}")));
+    }
+
+    if let Some(flags) = &info.config.flags {
+        args.extend(flags.iter().cloned());
+    }
+
+    args.push(format!("-I{}", export_include_dir(info)));
+
+    if info.kind == ProjectType::library {
+        args.extend([
+            String::from("-Iinclude"),
+            String::from("-DSPORK_EXPORT"),
+            format!("-D{}", export_building_define(&info.name)),
+        ]);
+
+        // Only symbols marked with the export header's macro are exported
+        if info.target.os != OperatingSystem::Windows {
+            args.extend(["-fPIC", "-fvisibility=hidden"].map(String::from));
+        }
+    } else if let Some(deps) = info.dependencies.clone() {
+        for (dep_path, _) in deps {
+            args.push(format!("-I{dep_path}/include"));
+        }
+    }
+
+    if info.release {
+        args.extend(["-O3", "-s"].map(String::from));
     } else {
-        Ok(false)
+        args.extend(["-O0", "-g", "-DSPORK_DEBUG"].map(String::from));
     }
+
+    Ok(args)
 }
 
-fn common_build_cmd(info: &BuildInfo) -> Command {
-    let mut cmd = Command::new("zig");
-    cmd.args(["cc", "-std=c17", "-Wall", "-Wextra", "-Wpedantic"]);
+fn common_build_cmd(info: &BuildInfo) -> FatalResult<Command> {
+    let mut cmd = toolchain().cc_cmd();
+    cmd.args(common_args(info)?);
+    Ok(cmd)
+}
 
-    cmd.args(["-target", &info.target.ziggified()]);
+fn common_args(info: &BuildInfo) -> FatalResult<Vec<String>> {
+    let mut args = ["-std=c17", "-Wall", "-Wextra", "-Wpedantic"]
+        .map(String::from)
+        .to_vec();
 
-    cmd
+    args.extend(toolchain().target_args(&info.target)?);
+
+    if let Some(sanitize) = Sanitizer::cc_arg(&info.sanitizers) {
+        args.push(sanitize);
+    }
+
+    if info.coverage {
+        args.extend(toolchain().coverage_args()?);
+    }
+
+    Ok(args)
 }
 
-fn out_dir(info: &BuildInfo) -> String {
-    let prefix = unsafe { format!("{LAUNCH_DIR}/bin/{}", info.target) };
+pub fn out_dir(info: &BuildInfo) -> String {
+    let prefix = format!("{}/bin/{}", launch_dir(), info.target);
 
-    if info.release {
-        format!("{prefix}/release")
+    let profile = info.profile();
+
+    // Instrumented code can't be linked with regular code, so it gets its own directory
+    let mut variants: Vec<_> = info.sanitizers.iter().map(|s| s.to_string()).collect();
+    if info.coverage {
+        variants.push(String::from("coverage"));
+    }
+
+    if variants.is_empty() {
+        format!("{prefix}/{profile}")
     } else {
-        format!("{prefix}/debug")
+        format!("{prefix}/{profile}-{}", variants.join("-"))
     }
 }
 
 #[derive(Clone)]
 pub struct Dependency {
-    name: String,
-    deps: Option<Dependencies>,
+    pub name: String,
+    pub metadata: Metadata,
+    pub kind: ProjectType,
+    /// The dependency's directory, relative to the root project.
+    pub path: String,
+    pub targets: Option<Vec<String>>,
+    config: TargetConfig,
+    hooks: Hooks,
+    resources: BTreeMap<String, String>,
+    pub deps: Option<Dependencies>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Dependencies {
     path_to_deps: HashMap<String, Dependency>,
 }
 
 impl Dependencies {
-    pub fn new(paths: Vec<String>, target: &Target) -> FatalResult<Self> {
+    /// The direct dependencies, sorted by path.
+    pub fn iter(&self) -> impl Iterator<Item = &Dependency> {
+        let mut deps: Vec<_> = self.path_to_deps.values().collect();
+        deps.sort_by(|a, b| a.path.cmp(&b.path));
+        deps.into_iter()
+    }
+
+    /// The directories of every dependency, including those of dependencies, relative to the
+    /// project.
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+
+        for dep in self.path_to_deps.values() {
+            paths.push(dep.path.clone());
+
+            if let Some(deps) = &dep.deps {
+                paths.extend(deps.paths());
+            }
+        }
+
+        paths.sort();
+        paths
+    }
+
+    /// Names of every library in the dependency graph, including indirect dependencies.
+    pub fn library_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+
+        for dep in self.path_to_deps.values() {
+            if let Some(deps) = &dep.deps {
+                names.extend(deps.library_names());
+            }
+            names.push(dep.name.clone());
+        }
+
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Resolves the dependencies listed by a project, along with the features it enables for them
+    /// (by name, from its `dependency_features`).
+    pub fn new(
+        paths: Vec<String>,
+        target: &Target,
+        features: Option<&HashMap<String, Vec<String>>>,
+    ) -> FatalResult<Self> {
+        Self::with_base(paths, target, features, ".")
+    }
+
+    /// Resolves dependency paths that are relative to the project in `base`, since each project
+    /// lists its dependencies relative to itself.
+    fn with_base(
+        paths: Vec<String>,
+        target: &Target,
+        features: Option<&HashMap<String, Vec<String>>>,
+        base: &str,
+    ) -> FatalResult<Self> {
         let mut path_to_deps = HashMap::new();
 
         for path in paths {
-            let spork_file = parse_spork_file(&format!("{path}/{SPORK_FILE_NAME}"))?;
+            let resolved_path = if base == "." || Path::new(&path).is_absolute() {
+                path.clone()
+            } else {
+                format!("{base}/{path}")
+            };
+
+            let spork_file = parse_spork_file(&format!("{resolved_path}/{SPORK_FILE_NAME}"))?;
+            spork_file.warn_invalid_metadata();
 
             if spork_file.project.kind != ProjectType::library {
                 return Err(FatalError::NoExecutableDependencies {
@@ -374,7 +1068,7 @@ impl Dependencies {
                 });
             }
 
-            if let Some(targets) = spork_file.project.targets {
+            if let Some(targets) = &spork_file.project.targets {
                 if !targets.contains(&target.to_string()) {
                     return Err(FatalError::NoTargetSupportDependency {
                         dep: spork_file.project.name,
@@ -383,14 +1077,39 @@ impl Dependencies {
                 }
             }
 
+            let mut config = spork_file.target_config(target)?;
+            if let Some(enabled) =
+                features.and_then(|features| features.get(&spork_file.project.name))
+            {
+                spork_file.project.check_features(enabled)?;
+                config
+                    .defines
+                    .get_or_insert_with(Vec::new)
+                    .extend(enabled.iter().map(|feature| feature_define(feature)));
+            }
+
+            let deps = match config.dependencies.clone() {
+                Some(deps) => Some(Dependencies::with_base(
+                    deps,
+                    target,
+                    spork_file.dependency_features.as_ref(),
+                    &resolved_path,
+                )?),
+                None => None,
+            };
+
             path_to_deps.insert(
                 path,
                 Dependency {
+                    metadata: spork_file.project.metadata(),
                     name: spork_file.project.name,
-                    deps: match spork_file.project.dependencies {
-                        Some(deps) => Some(Dependencies::new(deps, target)?),
-                        None => None,
-                    },
+                    kind: spork_file.project.kind,
+                    path: resolved_path,
+                    targets: spork_file.project.targets,
+                    config,
+                    hooks: spork_file.hooks.unwrap_or_default(),
+                    resources: spork_file.resources.unwrap_or_default(),
+                    deps,
                 },
             );
         }
@@ -399,6 +1118,22 @@ impl Dependencies {
     }
 }
 
+/// The macro defined while building a library with a feature enabled, e.g. `SPORK_FEATURE_SIMD`.
+pub fn feature_define(feature: &str) -> String {
+    let name: String = feature
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    format!("SPORK_FEATURE_{name}")
+}
+
 impl IntoIterator for Dependencies {
     type Item = (String, Dependency);
 
@@ -408,3 +1143,117 @@ impl IntoIterator for Dependencies {
         self.path_to_deps.into_iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn obj_paths_mirror_sources() {
+        assert_eq!(obj_path("out", "src/main.c"), "out/obj/src/main.o");
+        assert_eq!(
+            obj_path("out", "./src/net/socket.c"),
+            "out/obj/src/net/socket.o"
+        );
+        assert_eq!(
+            obj_path("out", "out/resources/spork_resources.c"),
+            "out/obj/out/resources/spork_resources.o"
+        );
+    }
+
+    #[test]
+    fn obj_paths_dont_collide() {
+        let sources = [
+            "src/gen/foo.c",
+            "gen/foo.c",
+            "src/gen-foo.c",
+            "../gen/foo.c",
+            "@up/gen/foo.c",
+            "/gen/foo.c",
+        ];
+        let mut objects: Vec<_> = sources.iter().map(|src| obj_path("out", src)).collect();
+        objects.sort();
+        objects.dedup();
+
+        assert_eq!(objects.len(), sources.len());
+        assert!(objects.iter().all(|obj| obj.starts_with("out/obj/")));
+        assert!(objects.iter().all(|obj| !obj.contains("..")));
+    }
+
+    fn info(kind: ProjectType, version: Option<&str>) -> BuildInfo {
+        BuildInfo {
+            name: String::from("foo"),
+            metadata: Metadata {
+                version: version.map(String::from),
+                ..Default::default()
+            },
+            release: false,
+            sanitizers: Vec::new(),
+            coverage: false,
+            check: false,
+            kind,
+            target: Target::new("x86_64-linux", false).unwrap(),
+            config: TargetConfig::default(),
+            hooks: Hooks::default(),
+            resources: BTreeMap::new(),
+            bins: Vec::new(),
+            objects: Vec::new(),
+            outputs: Vec::new(),
+            dependencies: None,
+        }
+    }
+
+    #[test]
+    fn only_libraries_are_versioned() {
+        let version = lib_version(&info(ProjectType::library, Some("1.2.3-rc.1"))).unwrap();
+        assert_eq!([version.major, version.minor, version.patch], [1, 2, 3]);
+
+        assert!(lib_version(&info(ProjectType::library, None)).is_none());
+        assert!(lib_version(&info(ProjectType::executable, Some("1.2.3"))).is_none());
+        assert!(lib_version(&info(ProjectType::library, Some("1.0"))).is_none());
+    }
+
+    #[test]
+    fn rpaths_lead_back_to_the_library_dir() {
+        let out = "bin/x86_64-linux/debug";
+
+        assert_eq!(
+            rpath_args(&format!("{out}/app"), out),
+            ["-Wl,-rpath,$ORIGIN", "-Wl,-rpath,$ORIGIN/../lib"]
+        );
+        assert_eq!(
+            rpath_args(&format!("{out}/examples/hello"), out),
+            ["-Wl,-rpath,$ORIGIN/..", "-Wl,-rpath,$ORIGIN/../lib"]
+        );
+        assert_eq!(
+            rpath_args(&format!("{out}/tests/a/b/test"), out),
+            ["-Wl,-rpath,$ORIGIN/../../..", "-Wl,-rpath,$ORIGIN/../lib"]
+        );
+    }
+
+    #[test]
+    fn only_bin_roots_are_bin_sources() {
+        let bins = [
+            BinInfo {
+                name: String::from("tool"),
+                path: String::from("src/bin/tool.c"),
+            },
+            BinInfo {
+                name: String::from("app"),
+                path: String::from("./src/main.c"),
+            },
+        ];
+
+        assert!(is_bin_source("src/bin/tool.c", &bins));
+        assert!(is_bin_source("src/main.c", &bins));
+        assert!(!is_bin_source("src/bin/common/util.c", &bins));
+        assert!(!is_bin_source("src/bin/other.c", &bins));
+        assert!(!is_bin_source("src/main.c", &[]));
+    }
+
+    #[test]
+    fn features_define_macros() {
+        assert_eq!(feature_define("simd"), "SPORK_FEATURE_SIMD");
+        assert_eq!(feature_define("no-std"), "SPORK_FEATURE_NO_STD");
+    }
+}
@@ -0,0 +1,173 @@
+use std::{collections::BTreeMap, fmt::Write, path::PathBuf, sync::Mutex};
+
+use git2::{Repository, StatusOptions};
+use semver::Version;
+
+use crate::{
+    build::{out_dir, BuildInfo},
+    error::FatalResult,
+    toolchain::toolchain,
+    util::{mkdir_all, write_if_changed},
+};
+
+pub const BUILD_INFO_HEADER_NAME: &str = "spork_build_info.h";
+
+/// The git state of each repository seen during the current build, since a project and its
+/// dependencies often share one.
+static GIT_STATES: Mutex<BTreeMap<PathBuf, (String, bool)>> = Mutex::new(BTreeMap::new());
+
+/// Forgets the git states seen so far, so that the next build (e.g. in `spork watch`) sees new
+/// commits and changes.
+pub fn clear_git_states() {
+    GIT_STATES.lock().unwrap().clear();
+}
+
+/// Generates a header describing the project being built - its name and metadata, the target,
+/// profile and compiler, and the git commit it was built from - returning the directory it was
+/// written to.
+pub fn generate_build_info(info: &BuildInfo) -> FatalResult<String> {
+    let dir = format!("{}/build_info/{}", out_dir(info), info.name);
+    let path = format!("{dir}/{BUILD_INFO_HEADER_NAME}");
+
+    let metadata = &info.metadata;
+    let toolchain = toolchain();
+    let (git_hash, git_dirty) = git_state();
+
+    let mut header = String::from("#pragma once\n\n// Generated by spork - do not edit\n\n");
+    for (name, value) in [
+        ("SPORK_PROJECT_NAME", info.name.as_str()),
+        (
+            "SPORK_PROJECT_VERSION",
+            metadata.version.as_deref().unwrap_or_default(),
+        ),
+        (
+            "SPORK_PROJECT_AUTHORS",
+            &metadata.authors.as_deref().unwrap_or_default().join(", "),
+        ),
+        (
+            "SPORK_PROJECT_DESCRIPTION",
+            metadata.description.as_deref().unwrap_or_default(),
+        ),
+        (
+            "SPORK_PROJECT_LICENSE",
+            metadata.license.as_deref().unwrap_or_default(),
+        ),
+        (
+            "SPORK_PROJECT_REPOSITORY",
+            metadata.repository.as_deref().unwrap_or_default(),
+        ),
+        ("SPORK_TARGET", &info.target.to_string()),
+        (
+            "SPORK_TARGET_TRIPLE",
+            &toolchain.target_triple(&info.target),
+        ),
+        ("SPORK_TARGET_ARCH", &info.target.arch.to_string()),
+        ("SPORK_TARGET_OS", &info.target.os.to_string()),
+        ("SPORK_PROFILE", info.profile()),
+        ("SPORK_COMPILER", &toolchain.kind.to_string()),
+        ("SPORK_COMPILER_VERSION", &toolchain.version()?),
+        ("SPORK_GIT_HASH", &git_hash),
+    ] {
+        writeln!(header, "#define {name} \"{}\"", escape(value)).unwrap();
+    }
+    writeln!(header, "#define SPORK_GIT_DIRTY {}", u8::from(git_dirty)).unwrap();
+
+    if let Some(version) = metadata
+        .version
+        .as_deref()
+        .and_then(|version| Version::parse(version).ok())
+    {
+        writeln!(
+            header,
+            "#define SPORK_PROJECT_VERSION_MAJOR {}",
+            version.major
+        )
+        .unwrap();
+        writeln!(
+            header,
+            "#define SPORK_PROJECT_VERSION_MINOR {}",
+            version.minor
+        )
+        .unwrap();
+        writeln!(
+            header,
+            "#define SPORK_PROJECT_VERSION_PATCH {}",
+            version.patch
+        )
+        .unwrap();
+    }
+
+    mkdir_all(&dir)?;
+    write_if_changed(&path, &header)?;
+
+    Ok(dir)
+}
+
+/// The hash of the commit checked out in the project's repository, and whether tracked files
+/// have uncommitted changes. Projects outside a repository (or without commits) get no hash.
+fn git_state() -> (String, bool) {
+    let Ok(repo) = Repository::discover(".") else {
+        return (String::new(), false);
+    };
+
+    let mut states = GIT_STATES.lock().unwrap();
+    if let Some(state) = states.get(repo.path()) {
+        return state.clone();
+    }
+
+    let hash = match repo.head().and_then(|head| head.peel_to_commit()) {
+        Ok(commit) => commit.id().to_string(),
+        Err(_) => String::new(),
+    };
+
+    let mut options = StatusOptions::new();
+    options.include_untracked(false).include_ignored(false);
+    let dirty = repo
+        .statuses(Some(&mut options))
+        .is_ok_and(|statuses| !statuses.is_empty());
+
+    states.insert(repo.path().to_path_buf(), (hash.clone(), dirty));
+    (hash, dirty)
+}
+
+/// Escapes a value for a C string literal. Other control characters are written as three-digit
+/// octal escapes, so a digit after them can't be read as part of the escape.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_ascii_control() => write!(escaped, "\\{:03o}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape;
+
+    #[test]
+    fn escapes_quotes_and_backslashes() {
+        assert_eq!(escape(r#"say "C:\hi""#), r#"say \"C:\\hi\""#);
+    }
+
+    #[test]
+    fn escapes_control_characters() {
+        assert_eq!(escape("one\ntwo\r\tthree"), r"one\ntwo\r\tthree");
+        assert_eq!(escape("bell\x071"), r"bell\0071");
+        assert_eq!(escape("del\x7f"), r"del\177");
+    }
+
+    #[test]
+    fn keeps_other_characters() {
+        assert_eq!(escape("héllo wörld ✓"), "héllo wörld ✓");
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::{
+    build::{out_dir, run_cc, BuildInfo},
+    error::{FatalError, FatalResult},
+    hooks::build_env,
+    progress,
+    targets::{OperatingSystem, Target},
+    toolchain::toolchain,
+    util::{mkdir_all, mkfile, modified},
+    warning,
+};
+
+const BUILD_SCRIPT_PATH: &str = "build.c";
+
+/// Directives printed by a build script, as `spork:<key>=<value>` lines on stdout.
+#[derive(Default)]
+pub struct BuildScriptOutput {
+    pub cflags: Vec<String>,
+    pub defines: Vec<String>,
+    pub sources: Vec<String>,
+    pub rerun_if_changed: Vec<String>,
+}
+
+impl BuildScriptOutput {
+    fn parse(stdout: &str) -> Self {
+        let mut output = Self::default();
+
+        for line in stdout.lines() {
+            let Some(directive) = line.trim().strip_prefix("spork:") else {
+                continue;
+            };
+
+            let Some((key, value)) = directive.split_once('=') else {
+                warning!("ignoring malformed build script directive '{line}'");
+                continue;
+            };
+
+            let value = value.to_string();
+            match key {
+                "cflag" => output.cflags.push(value),
+                "define" => output.defines.push(value),
+                "source" => output.sources.push(value),
+                "rerun-if-changed" => output.rerun_if_changed.push(value),
+                _ => warning!("ignoring unknown build script directive '{key}'"),
+            }
+        }
+
+        output
+    }
+}
+
+/// Compiles `build.c` for the host and runs it from the project directory.
+///
+/// The previous output is reused when the script hasn't changed and it declared
+/// `rerun-if-changed` paths, none of which have been modified since it last ran.
+pub fn run_build_script(info: &BuildInfo) -> FatalResult<Option<BuildScriptOutput>> {
+    if !Path::new(BUILD_SCRIPT_PATH).is_file() {
+        return Ok(None);
+    }
+
+    let host = Target::host()?;
+    let script_dir = format!("{}/build_script/{}", out_dir(info), info.name);
+    let script_path = if host.os == OperatingSystem::Windows {
+        format!("{script_dir}/build.exe")
+    } else {
+        format!("{script_dir}/build")
+    };
+    let output_path = format!("{script_dir}/output");
+
+    mkdir_all(&script_dir)?;
+
+    let is_stale = |path: &str, input: &str| match (modified(path), modified(input)) {
+        (Some(output_time), Some(input_time)) => input_time > output_time,
+        _ => true,
+    };
+
+    let mut rerun = is_stale(&script_path, BUILD_SCRIPT_PATH);
+    if rerun {
+        progress!("compiling build script of '{}'...", info.name);
+
+        let toolchain = toolchain();
+        let mut cmd = toolchain.cc_cmd();
+        cmd.arg("-std=c17").args(toolchain.target_args(&host)?);
+        cmd.args([BUILD_SCRIPT_PATH, "-o", &script_path]);
+
+        if !run_cc(&mut cmd)? {
+            return Err(FatalError::BuildScriptCompilationFailed);
+        }
+    }
+
+    if !rerun {
+        rerun = match fs::read_to_string(&output_path) {
+            Ok(stdout) => {
+                let output = BuildScriptOutput::parse(&stdout);
+                output.rerun_if_changed.is_empty()
+                    || output
+                        .rerun_if_changed
+                        .iter()
+                        .any(|path| is_stale(&output_path, path))
+            }
+            Err(_) => true,
+        };
+    }
+
+    if !rerun {
+        let stdout = fs::read_to_string(&output_path).unwrap_or_default();
+        return Ok(Some(BuildScriptOutput::parse(&stdout)));
+    }
+
+    progress!("running build script of '{}'...", info.name);
+
+    let mut cmd = Command::new(&script_path);
+    cmd.envs(build_env(info));
+    cmd.env("SPORK_HOST", host.to_string());
+    cmd.stderr(Stdio::inherit());
+
+    let output = match cmd.output() {
+        Ok(res) => res,
+        Err(err) => {
+            return Err(FatalError::FailedRunOutput {
+                path: script_path,
+                err,
+            })
+        }
+    };
+
+    if !output.status.success() {
+        return Err(FatalError::BuildScriptFailed {
+            code: output.status.code(),
+        });
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    mkfile(&output_path, &stdout)?;
+
+    Ok(Some(BuildScriptOutput::parse(&stdout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_directives() {
+        let output = BuildScriptOutput::parse(
+            "spork:cflag=-Wno-unused\n\
+             spork:define=VERSION=\"1.0\"\n\
+             spork:source=gen/table.c\n  spork:rerun-if-changed=data/table.csv  \n\
+             spork:define=FAST\n",
+        );
+
+        assert_eq!(output.cflags, ["-Wno-unused"]);
+        assert_eq!(output.defines, ["VERSION=\"1.0\"", "FAST"]);
+        assert_eq!(output.sources, ["gen/table.c"]);
+        assert_eq!(output.rerun_if_changed, ["data/table.csv"]);
+    }
+
+    #[test]
+    fn ignores_other_lines() {
+        let output = BuildScriptOutput::parse(
+            "generating tables...\n\
+             spork:cflag\n\
+             spork:unknown=1\n\
+             note: spork:define=X\n",
+        );
+
+        assert!(output.cflags.is_empty());
+        assert!(output.defines.is_empty());
+        assert!(output.sources.is_empty());
+        assert!(output.rerun_if_changed.is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+use std::{iter::Peekable, str::Chars};
+
+use crate::{
+    error::{FatalError, FatalResult},
+    targets::{Architecture, OperatingSystem, Target},
+};
+
+/// A `cfg(...)` predicate used to select target-specific configuration tables.
+///
+/// Supported forms are `os = "..."`, `arch = "..."`, `all(...)`, `any(...)` and `not(...)`.
+#[derive(Debug, PartialEq)]
+pub enum Cfg {
+    Os(OperatingSystem),
+    Arch(Architecture),
+    All(Vec<Cfg>),
+    Any(Vec<Cfg>),
+    Not(Box<Cfg>),
+}
+
+impl Cfg {
+    /// Parses a full `cfg(...)` specifier, as found in a `[target.'cfg(...)']` table name.
+    pub fn new(spec: &str) -> FatalResult<Self> {
+        let mut parser = CfgParser {
+            spec,
+            chars: spec.chars().peekable(),
+        };
+
+        parser.expect_ident("cfg")?;
+        parser.expect('(')?;
+        let cfg = parser.predicate()?;
+        parser.expect(')')?;
+
+        if parser.next_token().is_some() {
+            return Err(parser.error());
+        }
+
+        Ok(cfg)
+    }
+
+    pub fn matches(&self, target: &Target) -> bool {
+        match self {
+            Self::Os(os) => &target.os == os,
+            Self::Arch(arch) => &target.arch == arch,
+            Self::All(cfgs) => cfgs.iter().all(|cfg| cfg.matches(target)),
+            Self::Any(cfgs) => cfgs.iter().any(|cfg| cfg.matches(target)),
+            Self::Not(cfg) => !cfg.matches(target),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Punct(char),
+}
+
+struct CfgParser<'a> {
+    spec: &'a str,
+    chars: Peekable<Chars<'a>>,
+}
+
+impl CfgParser<'_> {
+    fn predicate(&mut self) -> FatalResult<Cfg> {
+        let ident = match self.next_token() {
+            Some(Token::Ident(ident)) => ident,
+            _ => return Err(self.error()),
+        };
+
+        match ident.as_str() {
+            "os" | "arch" => {
+                self.expect('=')?;
+                let value = match self.next_token() {
+                    Some(Token::Str(value)) => value,
+                    _ => return Err(self.error()),
+                };
+
+                if ident == "os" {
+                    Ok(Cfg::Os(OperatingSystem::new(&value)?))
+                } else {
+                    Ok(Cfg::Arch(Architecture::new(&value)?))
+                }
+            }
+            "all" | "any" => {
+                self.expect('(')?;
+                let mut cfgs = Vec::new();
+
+                loop {
+                    self.skip_whitespace();
+                    if self.chars.peek() == Some(&')') {
+                        self.chars.next();
+                        break;
+                    }
+
+                    cfgs.push(self.predicate()?);
+
+                    match self.next_token() {
+                        Some(Token::Punct(',')) => continue,
+                        Some(Token::Punct(')')) => break,
+                        _ => return Err(self.error()),
+                    }
+                }
+
+                if ident == "all" {
+                    Ok(Cfg::All(cfgs))
+                } else {
+                    Ok(Cfg::Any(cfgs))
+                }
+            }
+            "not" => {
+                self.expect('(')?;
+                let cfg = self.predicate()?;
+                self.expect(')')?;
+                Ok(Cfg::Not(Box::new(cfg)))
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        self.skip_whitespace();
+
+        let c = self.chars.next()?;
+        match c {
+            '(' | ')' | ',' | '=' => Some(Token::Punct(c)),
+            '"' => {
+                let mut value = String::new();
+                for c in self.chars.by_ref() {
+                    if c == '"' {
+                        return Some(Token::Str(value));
+                    }
+                    value.push(c);
+                }
+
+                // Unterminated string
+                Some(Token::Punct('"'))
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = String::from(c);
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    ident.push(c);
+                    self.chars.next();
+                }
+
+                Some(Token::Ident(ident))
+            }
+            c => Some(Token::Punct(c)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, punct: char) -> FatalResult<()> {
+        if self.next_token() == Some(Token::Punct(punct)) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn expect_ident(&mut self, ident: &str) -> FatalResult<()> {
+        if self.next_token() == Some(Token::Ident(ident.to_string())) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn error(&self) -> FatalError {
+        FatalError::InvalidTargetCfg {
+            cfg: self.spec.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(spec: &str) -> Target {
+        Target::new(spec, false).unwrap()
+    }
+
+    #[test]
+    fn parses_single_predicates() {
+        assert_eq!(
+            Cfg::new(r#"cfg(os = "linux")"#).unwrap(),
+            Cfg::Os(OperatingSystem::Linux)
+        );
+        assert_eq!(
+            Cfg::new(r#"cfg(arch = "x86")"#).unwrap(),
+            Cfg::Arch(Architecture::X86)
+        );
+    }
+
+    #[test]
+    fn parses_nested_combinators() {
+        let cfg =
+            Cfg::new(r#"cfg(all(any(os = "linux", os = "windows"), not(arch = "x86")))"#).unwrap();
+
+        assert_eq!(
+            cfg,
+            Cfg::All(vec![
+                Cfg::Any(vec![
+                    Cfg::Os(OperatingSystem::Linux),
+                    Cfg::Os(OperatingSystem::Windows),
+                ]),
+                Cfg::Not(Box::new(Cfg::Arch(Architecture::X86))),
+            ])
+        );
+
+        assert!(cfg.matches(&target("x86_64-linux")));
+        assert!(cfg.matches(&target("x86_64-windows")));
+        assert!(!cfg.matches(&target("x86-linux")));
+        assert!(!cfg.matches(&target("x86_64-freestanding")));
+    }
+
+    #[test]
+    fn empty_lists_match_like_iterators() {
+        let all = Cfg::new("cfg(all())").unwrap();
+        let any = Cfg::new("cfg(any())").unwrap();
+
+        assert_eq!(all, Cfg::All(Vec::new()));
+        assert!(all.matches(&target("x86_64-linux")));
+        assert!(!any.matches(&target("x86_64-linux")));
+    }
+
+    #[test]
+    fn ignores_whitespace() {
+        assert_eq!(
+            Cfg::new("  cfg ( any ( os=\"linux\" ,\tarch = \"x86\" ) ) ").unwrap(),
+            Cfg::Any(vec![
+                Cfg::Os(OperatingSystem::Linux),
+                Cfg::Arch(Architecture::X86),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_specs() {
+        for spec in [
+            r#"cfg(os = "linux)"#,
+            r#"cfg(os = "linux") extra"#,
+            r#"cfg(os = "linux"))"#,
+            r#"cfg(vendor = "pc")"#,
+            r#"cfg(os = "macos")"#,
+            r#"cfg(os = linux)"#,
+            r#"cfg(all(os = "linux",, arch = "x86"))"#,
+            r#"cfg(not(os = "linux", arch = "x86"))"#,
+            r#"cfg(not())"#,
+            r#"os = "linux""#,
+            "cfg()",
+            "",
+        ] {
+            assert!(Cfg::new(spec).is_err(), "'{spec}' should be rejected");
+        }
+    }
+}
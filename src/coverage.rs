@@ -0,0 +1,103 @@
+use std::fs;
+
+use glob::glob;
+
+use crate::{
+    build::{ArtifactKind, BuildInfo},
+    error::{FatalError, FatalResult},
+    message::redirect_stdout,
+    progress,
+    sources::collect_sources,
+    success,
+    util::{launch_dir, mkdir_all, mkfile, run_tool, tool_cmd},
+    warning,
+};
+
+pub fn coverage_dir(info: &BuildInfo) -> String {
+    format!("{}/bin/{}/coverage", launch_dir(), info.target)
+}
+
+/// Removes the raw profiles of previous runs, so they don't skew the report.
+pub fn prepare(info: &BuildInfo) -> FatalResult<()> {
+    let raw_dir = format!("{}/raw", coverage_dir(info));
+    let _ = fs::remove_dir_all(&raw_dir);
+    mkdir_all(&raw_dir)
+}
+
+pub fn profile_file(info: &BuildInfo, test_name: &str) -> String {
+    format!("{}/raw/{test_name}-%p.profraw", coverage_dir(info))
+}
+
+/// Merges the raw profiles written by the tests and reports the coverage of the project's own
+/// sources, as a terminal summary, an lcov file and an HTML report.
+pub fn report(info: &BuildInfo) -> FatalResult<()> {
+    let dir = coverage_dir(info);
+    progress!("generating coverage report...");
+
+    let raw_profiles: Vec<_> = glob(&format!("{dir}/raw/*.profraw"))
+        .unwrap()
+        .flatten()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+
+    if raw_profiles.is_empty() {
+        warning!("no coverage data was written - skipping coverage report");
+        return Ok(());
+    }
+
+    let profdata_path = format!("{dir}/coverage.profdata");
+    let mut cmd = tool_cmd("LLVM_PROFDATA", "llvm-profdata");
+    cmd.args(["merge", "-sparse", "-o", &profdata_path]);
+    cmd.args(&raw_profiles);
+    run_tool(&mut cmd, "llvm-profdata")?;
+
+    // Tests and dependencies are left out by only reporting on the sources in the project
+    let mut objects = info
+        .outputs
+        .iter()
+        .filter(|artifact| matches!(artifact.kind, ArtifactKind::Test | ArtifactKind::Library))
+        .map(|artifact| artifact.path.clone());
+
+    let Some(first_object) = objects.next() else {
+        return Ok(());
+    };
+
+    let mut cov_args = vec![format!("-instr-profile={profdata_path}"), first_object];
+    for object in objects {
+        cov_args.push(format!("-object={object}"));
+    }
+    cov_args.extend(collect_sources(&info.config, &info.target)?);
+
+    let mut cmd = tool_cmd("LLVM_COV", "llvm-cov");
+    cmd.arg("report").args(&cov_args);
+    redirect_stdout(&mut cmd);
+    run_tool(&mut cmd, "llvm-cov")?;
+
+    let mut cmd = tool_cmd("LLVM_COV", "llvm-cov");
+    cmd.args(["export", "-format=lcov"]).args(&cov_args);
+    let lcov = match cmd.output() {
+        Ok(res) if res.status.success() => String::from_utf8_lossy(&res.stdout).to_string(),
+        Ok(_) => {
+            return Err(FatalError::ToolFailed {
+                tool: String::from("llvm-cov"),
+            })
+        }
+        Err(err) => {
+            return Err(FatalError::FailedRunTool {
+                tool: String::from("llvm-cov"),
+                err,
+            })
+        }
+    };
+    mkfile(&format!("{dir}/lcov.info"), &lcov)?;
+
+    let mut cmd = tool_cmd("LLVM_COV", "llvm-cov");
+    cmd.args(["show", "-format=html", &format!("-output-dir={dir}/html")])
+        .args(&cov_args);
+    redirect_stdout(&mut cmd);
+    run_tool(&mut cmd, "llvm-cov")?;
+
+    success!("wrote coverage report to '{dir}'");
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{FatalError, FatalResult},
+    fatal_error, progress,
+    project::{parse_spork_file, ProjectFile},
+    success,
+    targets::Target,
+    toolchain::{set_toolchain, toolchain},
+    warning, SPORK_FILE_NAME,
+};
+
+const OPTIONAL_TOOLS: [(&str, &str); 7] = [
+    ("clang-format", "spork fmt"),
+    ("clang-tidy", "spork lint"),
+    ("llvm-profdata", "spork test --coverage"),
+    ("llvm-cov", "spork test --coverage"),
+    ("pkg-config", "finding system libraries"),
+    ("qemu-x86_64", "running x86_64 builds on other hosts"),
+    ("qemu-i386", "running x86 builds on other hosts"),
+];
+
+/// Checks the environment spork runs in - the toolchain, the host target, optional tools and the
+/// project file - and reports anything that's missing or broken.
+pub fn doctor() -> FatalResult<()> {
+    let mut problems = 0;
+
+    // The project's toolchain is used when there's a valid project here
+    let spork_file = if Path::new(SPORK_FILE_NAME).is_file() {
+        Some(parse_spork_file(SPORK_FILE_NAME))
+    } else {
+        None
+    };
+    if let Some(Ok(spork_file)) = &spork_file {
+        set_toolchain(spork_file.toolchain.as_ref());
+    }
+
+    let toolchain = toolchain();
+    progress!("toolchain: {}", toolchain.kind);
+
+    match find_in_path(toolchain.cc_program()) {
+        Some(path) => match toolchain.version() {
+            Ok(version) => success!("compiler: '{}' ({version})", path.display()),
+            Err(err) => {
+                fatal_error!("compiler: {err}");
+                problems += 1;
+            }
+        },
+        None => {
+            fatal_error!("compiler: '{}' not found in PATH", toolchain.cc_program());
+            problems += 1;
+        }
+    }
+
+    if let Some(expected) = &toolchain.zig_version {
+        match toolchain.check_zig_version() {
+            Ok(()) => success!("zig version: matches {expected}"),
+            Err(err) => {
+                fatal_error!("zig version: {err}");
+                problems += 1;
+            }
+        }
+    }
+
+    match Target::host() {
+        Ok(host) => success!("host target: {host}"),
+        Err(err) => {
+            fatal_error!("host target: {err}");
+            problems += 1;
+        }
+    }
+
+    let (major, minor, patch) = git2::Version::get().libgit2_version();
+    success!(
+        "git: libgit2 {major}.{minor}.{patch} (built in, used by 'spork new' and 'spork init')"
+    );
+
+    for (tool, used_for) in OPTIONAL_TOOLS {
+        match find_in_path(tool) {
+            Some(path) => success!("{tool}: '{}'", path.display()),
+            None => warning!("{tool}: not found (optional, used for {used_for})"),
+        }
+    }
+
+    match spork_file {
+        Some(Ok(spork_file)) => match check_spork_file(&spork_file) {
+            Ok(()) => success!("{SPORK_FILE_NAME}: valid"),
+            Err(err) => {
+                fatal_error!("{SPORK_FILE_NAME}: {err}");
+                problems += 1;
+            }
+        },
+        Some(Err(err)) => {
+            fatal_error!("{SPORK_FILE_NAME}: {err}");
+            problems += 1;
+        }
+        None => progress!("{SPORK_FILE_NAME}: not in a project directory"),
+    }
+
+    if problems == 0 {
+        success!("no problems found");
+        Ok(())
+    } else {
+        Err(FatalError::DoctorProblems { count: problems })
+    }
+}
+
+/// Checks the parts of a project file that aren't validated when it's parsed - its metadata, and
+/// the settings that are only resolved when building.
+fn check_spork_file(spork_file: &ProjectFile) -> FatalResult<()> {
+    spork_file.project.metadata().validate()?;
+
+    let targets = match &spork_file.project.targets {
+        Some(targets) => targets
+            .iter()
+            .map(|target| Target::new(target, false))
+            .collect::<FatalResult<Vec<_>>>()?,
+        None => vec![Target::host()?],
+    };
+
+    for target in &targets {
+        spork_file.target_config(target)?;
+    }
+
+    Ok(())
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return program.is_file().then(|| program.to_path_buf());
+    }
+
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths).find_map(|dir| {
+        let path = dir.join(program);
+        if path.is_file() {
+            return Some(path);
+        }
+
+        let path = path.with_extension("exe");
+        path.is_file().then_some(path)
+    })
+}
@@ -1,5 +1,7 @@
 use std::{fmt::Display, io, path::Path};
 
+use glob::PatternError;
+
 use crate::{targets::Target, SPORK_FILE_NAME};
 
 pub type FatalResult<T> = Result<T, FatalError>;
@@ -7,32 +9,280 @@ pub type FatalResult<T> = Result<T, FatalError>;
 #[derive(Debug)]
 pub enum FatalError {
     InvalidProjectName,
-    CannotCreateFile { path: String, err: io::Error },
-    CannotCreateDir { path: String, err: io::Error },
-    CannotGetCurrentDir { err: io::Error },
-    CannotReadFileInDir { path: String, err: io::Error },
-    CannotReadDir { path: String, err: io::Error },
-    CannotRemoveDir { path: String, err: io::Error },
+    CannotCreateFile {
+        path: String,
+        err: io::Error,
+    },
+    CannotCreateDir {
+        path: String,
+        err: io::Error,
+    },
+    CannotGetCurrentDir {
+        err: io::Error,
+    },
+    CannotReadFileInDir {
+        path: String,
+        err: io::Error,
+    },
+    CannotReadDir {
+        path: String,
+        err: io::Error,
+    },
+    CannotReadFile {
+        path: String,
+        err: io::Error,
+    },
+    CannotRemoveDir {
+        path: String,
+        err: io::Error,
+    },
+    CannotCopyFile {
+        path: String,
+        err: io::Error,
+    },
     CurrentDirInvalid,
     CurrentDirInvalidUTF8,
-    FileInvalidUTF8 { path: Box<Path> },
-    FailedRunGitInit { err: git2::Error },
-    FailedRunZigcc { err: io::Error },
-    FailedRunOutput { path: String, err: io::Error },
-    BuildFileParseError { err: toml::de::Error },
+    FileInvalidUTF8 {
+        path: Box<Path>,
+    },
+    FailedRunGitInit {
+        err: git2::Error,
+    },
+    FailedRunCompiler {
+        cc: String,
+        err: io::Error,
+    },
+    FailedRunOutput {
+        path: String,
+        err: io::Error,
+    },
+    FailedRunTool {
+        tool: String,
+        err: io::Error,
+    },
+    ToolFailed {
+        tool: String,
+    },
+    FailedRunHook {
+        hook: String,
+        err: io::Error,
+    },
+    HookFailed {
+        hook: String,
+        code: Option<i32>,
+    },
+    BuildFileParseError {
+        err: toml::de::Error,
+    },
     CompilationFailed,
+    BuildScriptCompilationFailed,
+    BuildScriptFailed {
+        code: Option<i32>,
+    },
     LinkFailed,
     CannotRunLib,
-    NoSporkToml { path: String },
+    TestsFailed {
+        names: Vec<String>,
+    },
+    BenchFailed {
+        name: String,
+    },
+    InvalidBaselineName {
+        name: String,
+    },
+    UnformattedFiles {
+        paths: Vec<String>,
+    },
+    LintIssues {
+        count: usize,
+    },
+    NoSuchBin {
+        name: String,
+    },
+    AmbiguousBin {
+        names: Vec<String>,
+    },
+    NoSuchExample {
+        name: String,
+    },
+    NoSporkToml {
+        path: String,
+    },
+    NoInstallManifest {
+        name: String,
+        path: String,
+    },
     NoSourceFiles,
+    NoProjectVersion,
+    InvalidVersion {
+        version: String,
+        err: semver::Error,
+    },
+    InvalidLicense {
+        err: spdx::ParseError,
+    },
+    InvalidGlob {
+        path: String,
+        err: PatternError,
+    },
+    InvalidResourceSymbol {
+        symbol: String,
+    },
+    NoResourceFiles {
+        path: String,
+    },
     NoSupportedTargets,
-    BadTarget { target: String },
-    InvalidTargetArch { arch: String },
-    InvalidTargetOS { os: String },
-    NoExecutableDependencies { name: String },
-    NoTargetSupportDependency { dep: String, target: Target },
-    CouldntGetWorkDir { err: io::Error },
-    CouldntChangeWorkDir { dir: String, err: io::Error },
+    BadTarget {
+        target: String,
+    },
+    InvalidTargetArch {
+        arch: String,
+    },
+    InvalidTargetOS {
+        os: String,
+    },
+    InvalidTargetCfg {
+        cfg: String,
+    },
+    InvalidSanitizer {
+        name: String,
+    },
+    IncompatibleSanitizers {
+        names: Vec<String>,
+    },
+    UnsupportedSanitizer {
+        name: String,
+        target: Target,
+    },
+    UnsupportedToolchainTarget {
+        kind: String,
+        target: Target,
+    },
+    UnsupportedToolchainCoverage {
+        kind: String,
+    },
+    ZigVersionMismatch {
+        expected: String,
+        found: String,
+    },
+    DoctorProblems {
+        count: usize,
+    },
+    NoExecutableDependencies {
+        name: String,
+    },
+    NoSuchDependency {
+        name: String,
+    },
+    AmbiguousDependency {
+        name: String,
+        paths: Vec<String>,
+    },
+    DependencyExists {
+        name: String,
+    },
+    NoSuchFeature {
+        dep: String,
+        feature: String,
+        offered: Vec<String>,
+    },
+    DependencyDirExists {
+        path: String,
+    },
+    FailedCloneDependency {
+        url: String,
+        err: git2::Error,
+    },
+    InvalidSporkToml,
+    NoTargetSupportDependency {
+        dep: String,
+        target: Target,
+    },
+    CannotWatch {
+        path: String,
+        err: notify::Error,
+    },
+    CouldntGetWorkDir {
+        err: io::Error,
+    },
+    CouldntChangeWorkDir {
+        dir: String,
+        err: io::Error,
+    },
+}
+
+impl FatalError {
+    /// The name of the variant, e.g. `CompilationFailed`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::InvalidProjectName => "InvalidProjectName",
+            Self::CannotCreateFile { .. } => "CannotCreateFile",
+            Self::CannotCreateDir { .. } => "CannotCreateDir",
+            Self::CannotGetCurrentDir { .. } => "CannotGetCurrentDir",
+            Self::CannotReadFileInDir { .. } => "CannotReadFileInDir",
+            Self::CannotReadDir { .. } => "CannotReadDir",
+            Self::CannotReadFile { .. } => "CannotReadFile",
+            Self::CannotRemoveDir { .. } => "CannotRemoveDir",
+            Self::CannotCopyFile { .. } => "CannotCopyFile",
+            Self::CurrentDirInvalid => "CurrentDirInvalid",
+            Self::CurrentDirInvalidUTF8 => "CurrentDirInvalidUTF8",
+            Self::FileInvalidUTF8 { .. } => "FileInvalidUTF8",
+            Self::FailedRunGitInit { .. } => "FailedRunGitInit",
+            Self::FailedRunCompiler { .. } => "FailedRunCompiler",
+            Self::FailedRunOutput { .. } => "FailedRunOutput",
+            Self::FailedRunTool { .. } => "FailedRunTool",
+            Self::ToolFailed { .. } => "ToolFailed",
+            Self::FailedRunHook { .. } => "FailedRunHook",
+            Self::HookFailed { .. } => "HookFailed",
+            Self::BuildFileParseError { .. } => "BuildFileParseError",
+            Self::CompilationFailed => "CompilationFailed",
+            Self::BuildScriptCompilationFailed => "BuildScriptCompilationFailed",
+            Self::BuildScriptFailed { .. } => "BuildScriptFailed",
+            Self::LinkFailed => "LinkFailed",
+            Self::CannotRunLib => "CannotRunLib",
+            Self::TestsFailed { .. } => "TestsFailed",
+            Self::BenchFailed { .. } => "BenchFailed",
+            Self::InvalidBaselineName { .. } => "InvalidBaselineName",
+            Self::UnformattedFiles { .. } => "UnformattedFiles",
+            Self::LintIssues { .. } => "LintIssues",
+            Self::NoSuchBin { .. } => "NoSuchBin",
+            Self::AmbiguousBin { .. } => "AmbiguousBin",
+            Self::NoSuchExample { .. } => "NoSuchExample",
+            Self::NoSporkToml { .. } => "NoSporkToml",
+            Self::NoInstallManifest { .. } => "NoInstallManifest",
+            Self::NoSourceFiles => "NoSourceFiles",
+            Self::NoProjectVersion => "NoProjectVersion",
+            Self::InvalidVersion { .. } => "InvalidVersion",
+            Self::InvalidLicense { .. } => "InvalidLicense",
+            Self::InvalidGlob { .. } => "InvalidGlob",
+            Self::InvalidResourceSymbol { .. } => "InvalidResourceSymbol",
+            Self::NoResourceFiles { .. } => "NoResourceFiles",
+            Self::NoSupportedTargets => "NoSupportedTargets",
+            Self::BadTarget { .. } => "BadTarget",
+            Self::InvalidTargetArch { .. } => "InvalidTargetArch",
+            Self::InvalidTargetOS { .. } => "InvalidTargetOS",
+            Self::InvalidTargetCfg { .. } => "InvalidTargetCfg",
+            Self::InvalidSanitizer { .. } => "InvalidSanitizer",
+            Self::IncompatibleSanitizers { .. } => "IncompatibleSanitizers",
+            Self::UnsupportedSanitizer { .. } => "UnsupportedSanitizer",
+            Self::UnsupportedToolchainTarget { .. } => "UnsupportedToolchainTarget",
+            Self::UnsupportedToolchainCoverage { .. } => "UnsupportedToolchainCoverage",
+            Self::ZigVersionMismatch { .. } => "ZigVersionMismatch",
+            Self::DoctorProblems { .. } => "DoctorProblems",
+            Self::NoExecutableDependencies { .. } => "NoExecutableDependencies",
+            Self::NoSuchDependency { .. } => "NoSuchDependency",
+            Self::AmbiguousDependency { .. } => "AmbiguousDependency",
+            Self::DependencyExists { .. } => "DependencyExists",
+            Self::NoSuchFeature { .. } => "NoSuchFeature",
+            Self::DependencyDirExists { .. } => "DependencyDirExists",
+            Self::FailedCloneDependency { .. } => "FailedCloneDependency",
+            Self::InvalidSporkToml => "InvalidSporkToml",
+            Self::NoTargetSupportDependency { .. } => "NoTargetSupportDependency",
+            Self::CannotWatch { .. } => "CannotWatch",
+            Self::CouldntGetWorkDir { .. } => "CouldntGetWorkDir",
+            Self::CouldntChangeWorkDir { .. } => "CouldntChangeWorkDir",
+        }
+    }
 }
 
 impl Display for FatalError {
@@ -55,9 +305,11 @@ impl Display for FatalError {
             Self::CannotReadDir { path, err } => {
                 write!(f, "couldn't read directory '{path}': {err}")
             }
+            Self::CannotReadFile { path, err } => write!(f, "couldn't read file '{path}': {err}"),
             Self::CannotRemoveDir { path, err } => {
                 write!(f, "couldn't remove directory '{path}': {err}")
             }
+            Self::CannotCopyFile { path, err } => write!(f, "couldn't copy file to '{path}': {err}"),
             Self::CurrentDirInvalid => write!(f, "current directory is invalid"),
             Self::CurrentDirInvalidUTF8 => {
                 write!(f, "current directory contains invalid UTF-8 encoded text")
@@ -72,22 +324,78 @@ impl Display for FatalError {
             Self::FailedRunGitInit { err } => {
                 write!(f, "failed to initialize a git repository: {err}")
             }
-            Self::FailedRunZigcc { err } => {
-                write!(f, "failed to run 'zig cc': {err}")
-            }
+            Self::FailedRunCompiler { cc, err } => match err.kind() {
+                io::ErrorKind::NotFound => write!(
+                    f,
+                    "couldn't find '{cc}' - make sure it's installed and in PATH, or run 'spork doctor'"
+                ),
+                _ => write!(f, "failed to run '{cc}': {err}"),
+            },
             Self::FailedRunOutput { path, err } => write!(f, "failed to run '{path}': {err}"),
+            Self::FailedRunTool { tool, err } => write!(f, "failed to run '{tool}': {err}"),
+            Self::ToolFailed { tool } => write!(f, "'{tool}' exited with an error"),
+            Self::FailedRunHook { hook, err } => write!(f, "failed to run hook '{hook}': {err}"),
+            Self::HookFailed { hook, code } => match code {
+                Some(code) => write!(f, "hook '{hook}' failed with exit code {code}"),
+                None => write!(f, "hook '{hook}' was terminated by a signal"),
+            },
             Self::BuildFileParseError { err } => {
                 writeln!(f, "failed to parse '{SPORK_FILE_NAME}':")?;
                 write!(f, "{err}")
             }
             Self::CompilationFailed => write!(f, "compilation failed"),
+            Self::BuildScriptCompilationFailed => write!(f, "compilation of build script failed"),
+            Self::BuildScriptFailed { code } => match code {
+                Some(code) => write!(f, "build script failed with exit code {code}"),
+                None => write!(f, "build script was terminated by a signal"),
+            },
             Self::LinkFailed => write!(f, "linking failed"),
             Self::CannotRunLib => write!(
                 f,
                 "only executable projects can be run (use 'spork build' instead)"
             ),
+            Self::TestsFailed { names } => write!(f, "{} tests failed: {}", names.len(), names.join(", ")),
+            Self::BenchFailed { name } => write!(f, "benchmark '{name}' failed"),
+            Self::InvalidBaselineName { name } => write!(
+                f,
+                "baseline name '{name}' is invalid - it can only contain letters, digits, '-' and '_'"
+            ),
+            Self::UnformattedFiles { paths } => write!(
+                f,
+                "{} files are not formatted - run 'spork fmt' to format them",
+                paths.len()
+            ),
+            Self::LintIssues { count } => write!(f, "lint found {count} issues"),
+            Self::NoSuchBin { name } => write!(f, "project has no executable named '{name}'"),
+            Self::AmbiguousBin { names } => write!(
+                f,
+                "project has multiple executables - use '--bin' to pick one of: {}",
+                names.join(", ")
+            ),
+            Self::NoSuchExample { name } => write!(f, "project has no example named '{name}'"),
             Self::NoSporkToml { path } => write!(f, "couldn't find a '{SPORK_FILE_NAME}' file at '{path}'"),
+            Self::NoInstallManifest { name, path } => write!(
+                f,
+                "'{name}' is not installed here - couldn't find install manifest '{path}'"
+            ),
             Self::NoSourceFiles => write!(f, "project has no source files"),
+            Self::NoProjectVersion => write!(
+                f,
+                "project has no 'version' in '{SPORK_FILE_NAME}' - it's needed to name the package"
+            ),
+            Self::InvalidVersion { version, err } => {
+                write!(f, "version '{version}' is not a semantic version: {err}")
+            }
+            Self::InvalidLicense { err } => {
+                writeln!(f, "license is not a valid SPDX expression:")?;
+                write!(f, "{err}")
+            }
+            Self::InvalidGlob { path, err } => write!(f, "invalid glob pattern '{path}': {err}"),
+            Self::InvalidResourceSymbol { symbol } => write!(
+                f,
+                "resource symbol '{symbol}' must be a valid C identifier"
+            ),
+            Self::NoResourceFiles { path } => write!(f, "resource '{path}' matches no files"),
             Self::NoSupportedTargets => write!(
                 f,
                 "unable to run - built target does not match host target of '{}'",
@@ -100,11 +408,64 @@ impl Display for FatalError {
                 write!(f, "target architecture '{arch}' is invalid")
             }
             Self::InvalidTargetOS { os } => write!(f, "target os '{os}' is invalid"),
+            Self::InvalidTargetCfg { cfg } => write!(
+                f,
+                "target table '{cfg}' must be a target or follow format 'cfg(os = \"...\")'"
+            ),
+            Self::InvalidSanitizer { name } => write!(
+                f,
+                "sanitizer '{name}' is invalid - valid sanitizers are 'address', 'undefined' and 'thread'"
+            ),
+            Self::IncompatibleSanitizers { names } => {
+                write!(f, "sanitizers {} can't be used together", names.join(" and "))
+            }
+            Self::UnsupportedSanitizer { name, target } => {
+                write!(f, "sanitizer '{name}' is not supported on target '{target}'")
+            }
+            Self::UnsupportedToolchainTarget { kind, target } => write!(
+                f,
+                "toolchain '{kind}' can't cross compile to target '{target}' - use 'zig' or 'clang' instead"
+            ),
+            Self::UnsupportedToolchainCoverage { kind } => write!(
+                f,
+                "toolchain '{kind}' doesn't support coverage - use 'zig' or 'clang' instead"
+            ),
+            Self::ZigVersionMismatch { expected, found } => write!(
+                f,
+                "project requires zig {expected} but zig {found} is installed"
+            ),
+            Self::DoctorProblems { count } => write!(f, "found {count} problems"),
             Self::NoExecutableDependencies { name } => write!(
                 f,
                 "dependencies may only be library projects - '{name}' points to an executable project"
             ),
+            Self::NoSuchDependency { name } => write!(f, "project has no dependency named '{name}'"),
+            Self::AmbiguousDependency { name, paths } => write!(
+                f,
+                "several dependencies are named '{name}' - use a path to pick one of: {}",
+                paths.join(", ")
+            ),
+            Self::DependencyExists { name } => write!(f, "project already depends on '{name}'"),
+            Self::NoSuchFeature {
+                dep,
+                feature,
+                offered,
+            } => {
+                if offered.is_empty() {
+                    write!(f, "'{dep}' has no feature '{feature}' - it doesn't offer any features")
+                } else {
+                    write!(
+                        f,
+                        "'{dep}' has no feature '{feature}' - its features are: {}",
+                        offered.join(", ")
+                    )
+                }
+            }
+            Self::DependencyDirExists { path } => write!(f, "'{path}' already exists - add it by path instead"),
+            Self::FailedCloneDependency { url, err } => write!(f, "failed to clone '{url}': {err}"),
+            Self::InvalidSporkToml => write!(f, "'{SPORK_FILE_NAME}' is not a valid project file"),
             Self::NoTargetSupportDependency { dep, target } => write!(f, "dependency '{dep}' does not support target '{target}'"),
+            Self::CannotWatch { path, err } => write!(f, "couldn't watch '{path}' for changes: {err}"),
             Self::CouldntGetWorkDir { err } => write!(f, "couldn't get working directory: {err}"),
             Self::CouldntChangeWorkDir { dir, err } => write!(f, "couldn't change working directory to '{dir}': {err}"),
         }
@@ -0,0 +1,59 @@
+use crate::{
+    build::{out_dir, BuildInfo},
+    error::FatalResult,
+    util::{mkdir_all, write_if_changed},
+};
+
+/// Directory of the generated export headers, which every build has on its include path.
+pub fn export_include_dir(info: &BuildInfo) -> String {
+    format!("{}/include", out_dir(info))
+}
+
+/// Path of a library's export header, included as `<name/name_export.h>`.
+pub fn export_header_path(info: &BuildInfo) -> String {
+    format!(
+        "{}/{name}/{name}_export.h",
+        export_include_dir(info),
+        name = info.name
+    )
+}
+
+/// The macro defined while building a library, which makes its `<NAME>_API` export rather than
+/// import symbols.
+pub fn export_building_define(name: &str) -> String {
+    format!("{}_BUILDING", name.to_uppercase())
+}
+
+/// Generates the header defining a library's `<NAME>_API` macro, which marks the symbols it
+/// exports - libraries are compiled with hidden visibility, so nothing else is.
+pub fn generate_export_header(info: &BuildInfo) -> FatalResult<()> {
+    let upper = info.name.to_uppercase();
+    let building = export_building_define(&info.name);
+
+    let header = format!(
+        r#"#pragma once
+
+// Generated by spork - mark public functions and variables with {upper}_API to export them.
+// Define {upper}_STATIC when linking against the static library.
+
+#if defined({upper}_STATIC)
+#define {upper}_API
+#elif defined(_WIN32) || defined(__CYGWIN__)
+#ifdef {building}
+#define {upper}_API __declspec(dllexport)
+#else
+#define {upper}_API __declspec(dllimport)
+#endif
+#elif defined(__GNUC__) || defined(__clang__)
+#define {upper}_API __attribute__((visibility("default")))
+#else
+#define {upper}_API
+#endif
+"#
+    );
+
+    let path = export_header_path(info);
+
+    mkdir_all(&format!("{}/{}", export_include_dir(info), info.name))?;
+    write_if_changed(&path, &header)
+}
@@ -0,0 +1,101 @@
+use std::fs;
+
+use crate::{
+    error::{FatalError, FatalResult},
+    message::redirect_stdout,
+    progress,
+    project::parse_spork_file,
+    sources::glob_paths,
+    success,
+    util::{run_tool, tool_cmd},
+    warning, SPORK_FILE_NAME,
+};
+
+const FORMAT_DIRS: [&str; 4] = ["src", "include", "tests", "examples"];
+
+/// Formats the project's C files with `clang-format`, using the project's `.clang-format` file.
+///
+/// In check mode files are left untouched, and the command fails if any of them isn't formatted.
+pub fn fmt(check: bool) -> FatalResult<()> {
+    parse_spork_file(SPORK_FILE_NAME)?;
+
+    let files = collect_format_files()?;
+    if files.is_empty() {
+        warning!("no files to format");
+        return Ok(());
+    }
+
+    if !check {
+        progress!("formatting {} files...", files.len());
+
+        let mut cmd = tool_cmd("CLANG_FORMAT", "clang-format");
+        cmd.args(["-i", "--style=file"]).args(&files);
+        redirect_stdout(&mut cmd);
+        run_tool(&mut cmd, "clang-format")?;
+
+        success!("formatted {} files", files.len());
+        return Ok(());
+    }
+
+    let mut unformatted = Vec::new();
+
+    for file in files {
+        let mut cmd = tool_cmd("CLANG_FORMAT", "clang-format");
+        cmd.args(["--style=file", &file]);
+
+        let formatted = match cmd.output() {
+            Ok(res) if res.status.success() => res.stdout,
+            Ok(_) => {
+                return Err(FatalError::ToolFailed {
+                    tool: String::from("clang-format"),
+                })
+            }
+            Err(err) => {
+                return Err(FatalError::FailedRunTool {
+                    tool: String::from("clang-format"),
+                    err,
+                })
+            }
+        };
+
+        let current = match fs::read(&file) {
+            Ok(res) => res,
+            Err(err) => return Err(FatalError::CannotReadFile { path: file, err }),
+        };
+
+        if formatted != current {
+            warning!("'{file}' is not formatted");
+            unformatted.push(file);
+        }
+    }
+
+    if unformatted.is_empty() {
+        success!("all files are formatted");
+        Ok(())
+    } else {
+        Err(FatalError::UnformattedFiles { paths: unformatted })
+    }
+}
+
+fn collect_format_files() -> FatalResult<Vec<String>> {
+    let mut files = Vec::new();
+
+    for dir in FORMAT_DIRS {
+        for path in glob_paths(&format!("{dir}/**/*.[ch]"))?.flatten() {
+            if !path.is_file() {
+                continue;
+            }
+
+            match path.to_str() {
+                Some(path) => files.push(path.to_string()),
+                None => {
+                    return Err(FatalError::FileInvalidUTF8 {
+                        path: path.as_path().into(),
+                    })
+                }
+            }
+        }
+    }
+
+    Ok(files)
+}
@@ -0,0 +1,56 @@
+use std::process::Command;
+
+use crate::{
+    build::{out_dir, BuildInfo},
+    error::{FatalError, FatalResult},
+    message::redirect_stdout,
+    progress,
+};
+
+/// Environment variables describing a build, passed to hooks.
+pub fn build_env(info: &BuildInfo) -> Vec<(&'static str, String)> {
+    vec![
+        ("SPORK_TARGET", info.target.to_string()),
+        ("SPORK_PROFILE", info.profile().to_string()),
+        ("SPORK_OUT_DIR", out_dir(info)),
+        ("SPORK_PROJECT_NAME", info.name.clone()),
+    ]
+}
+
+/// Runs each hook command through the system shell, stopping at the first one that fails.
+pub fn run_hooks(hooks: &[String], info: &BuildInfo) -> FatalResult<()> {
+    for hook in hooks {
+        progress!("running hook '{hook}'...");
+
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", hook]);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", hook]);
+            cmd
+        };
+        cmd.envs(build_env(info));
+
+        redirect_stdout(&mut cmd);
+
+        match cmd.status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                return Err(FatalError::HookFailed {
+                    hook: hook.clone(),
+                    code: status.code(),
+                })
+            }
+            Err(err) => {
+                return Err(FatalError::FailedRunHook {
+                    hook: hook.clone(),
+                    err,
+                })
+            }
+        }
+    }
+
+    Ok(())
+}
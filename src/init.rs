@@ -1,15 +1,159 @@
-use git2::Repository;
+use std::io::{self, Write};
+
+use clap::Args;
+use git2::{Config, Repository};
 
 use crate::{
     error::{FatalError, FatalResult},
-    project::{ProjectFile, ProjectInfo, ProjectType},
+    fatal_error,
+    project::{
+        validate_license, validate_version, Metadata, ProjectFile, ProjectInfo, ProjectType,
+    },
     success,
     util::{check_project_name, mkdir, mkfile},
     SPORK_FILE_NAME,
 };
 
-pub fn new_project(name: &str, path: &str, project_type: ProjectType) -> FatalResult<()> {
+const DEFAULT_VERSION: &str = "0.1.0";
+
+#[derive(Args)]
+pub struct MetadataArgs {
+    /// Version of the project [default: 0.1.0]
+    #[arg(long)]
+    version: Option<String>,
+
+    /// Author of the project, e.g. 'Name <email>' - can be repeated [default: the git user]
+    #[arg(long = "author")]
+    authors: Vec<String>,
+
+    /// One-line description of the project
+    #[arg(long)]
+    description: Option<String>,
+
+    /// License of the project, as an SPDX expression such as 'MIT OR Apache-2.0'
+    #[arg(long)]
+    license: Option<String>,
+
+    /// URL of the project's source repository
+    #[arg(long)]
+    repository: Option<String>,
+
+    /// Path of the project's readme file
+    #[arg(long)]
+    readme: Option<String>,
+
+    /// Ask for any metadata that wasn't given as a flag
+    #[arg(short, long)]
+    interactive: bool,
+}
+
+impl MetadataArgs {
+    /// Fills in the metadata that wasn't given, either with defaults or by asking for it.
+    pub fn into_metadata(self) -> FatalResult<Metadata> {
+        let mut metadata = Metadata {
+            version: self.version,
+            authors: (!self.authors.is_empty()).then_some(self.authors),
+            description: self.description,
+            license: self.license,
+            repository: self.repository,
+            readme: self.readme,
+        };
+
+        if self.interactive {
+            if metadata.version.is_none() {
+                metadata.version = prompt_valid("version", Some(DEFAULT_VERSION), |version| {
+                    validate_version(version).map(|_| ())
+                });
+            }
+
+            if metadata.authors.is_none() {
+                metadata.authors = prompt("authors (comma separated)", git_user().as_deref())
+                    .map(|authors| authors.split(',').map(|a| a.trim().to_string()).collect());
+            }
+
+            if metadata.description.is_none() {
+                metadata.description = prompt("description", None);
+            }
+
+            if metadata.license.is_none() {
+                metadata.license =
+                    prompt_valid("license (SPDX expression)", None, validate_license);
+            }
+
+            if metadata.repository.is_none() {
+                metadata.repository = prompt("repository", None);
+            }
+
+            if metadata.readme.is_none() {
+                metadata.readme = prompt("readme", None);
+            }
+        }
+
+        metadata
+            .version
+            .get_or_insert_with(|| DEFAULT_VERSION.to_string());
+        if metadata.authors.is_none() {
+            metadata.authors = git_user().map(|author| vec![author]);
+        }
+
+        metadata.validate()?;
+        Ok(metadata)
+    }
+}
+
+/// Asks for a value on stdin - an empty answer picks the default, if there is one.
+fn prompt(question: &str, default: Option<&str>) -> Option<String> {
+    match default {
+        Some(default) => print!("{question} [{default}]: "),
+        None => print!("{question}: "),
+    }
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return default.map(String::from);
+    }
+
+    match answer.trim() {
+        "" => default.map(String::from),
+        answer => Some(answer.to_string()),
+    }
+}
+
+/// Asks for a value until a valid one (or nothing) is given.
+fn prompt_valid(
+    question: &str,
+    default: Option<&str>,
+    validate: impl Fn(&str) -> FatalResult<()>,
+) -> Option<String> {
+    loop {
+        let answer = prompt(question, default)?;
+        match validate(&answer) {
+            Ok(()) => return Some(answer),
+            Err(err) => fatal_error!("{err}"),
+        }
+    }
+}
+
+/// The git user as `Name <email>`, which new projects are credited to by default.
+fn git_user() -> Option<String> {
+    let config = Config::open_default().ok()?;
+    let name = config.get_string("user.name").ok()?;
+
+    match config.get_string("user.email") {
+        Ok(email) => Some(format!("{name} <{email}>")),
+        Err(_) => Some(name),
+    }
+}
+
+pub fn new_project(
+    name: &str,
+    path: &str,
+    project_type: ProjectType,
+    metadata: MetadataArgs,
+) -> FatalResult<()> {
     check_project_name(name)?;
+    let metadata = metadata.into_metadata()?;
 
     mkdir(path)?;
     mkdir(&format!("{path}/src"))?;
@@ -22,17 +166,17 @@ pub fn new_project(name: &str, path: &str, project_type: ProjectType) -> FatalRe
     let clang_format_src = include_str!("../template/.clang-format");
     mkfile(&format!("{path}/.clang-format"), clang_format_src)?;
 
-    mkfile(&format!("{path}/.gitignore"), ".vscode\nbin\n")?;
+    mkfile(&format!("{path}/.gitignore"), ".vscode\nbin\ndist\n")?;
 
     if project_type == ProjectType::library {
         mkdir(&format!("{path}/include"))?;
         mkdir(&format!("{path}/include/{name}"))?;
 
-        let template_header = "#pragma once\n";
-        mkfile(&format!("{path}/include/{name}/entry.h"), template_header)?;
+        let template_header = format!("#pragma once\n\n#include <{name}/{name}_export.h>\n");
+        mkfile(&format!("{path}/include/{name}/entry.h"), &template_header)?;
     }
 
-    create_spork_file(name, path, project_type)?;
+    create_spork_file(name, path, project_type, metadata)?;
 
     if let Err(err) = Repository::init(path) {
         return Err(FatalError::FailedRunGitInit { err });
@@ -43,14 +187,36 @@ pub fn new_project(name: &str, path: &str, project_type: ProjectType) -> FatalRe
     Ok(())
 }
 
-fn create_spork_file(name: &str, path: &str, project_type: ProjectType) -> FatalResult<()> {
+fn create_spork_file(
+    name: &str,
+    path: &str,
+    project_type: ProjectType,
+    metadata: Metadata,
+) -> FatalResult<()> {
     let info_template = ProjectFile {
         project: ProjectInfo {
             name: name.to_string(),
+            version: metadata.version,
             kind: project_type,
+            authors: metadata.authors,
+            description: metadata.description,
+            license: metadata.license,
+            repository: metadata.repository,
+            readme: metadata.readme,
             targets: None,
             dependencies: None,
+            features: None,
+            sources: None,
+            exclude: None,
         },
+        target: None,
+        bin: None,
+        profile: None,
+        hooks: None,
+        resources: None,
+        lint: None,
+        toolchain: None,
+        dependency_features: None,
     };
 
     mkfile(
@@ -0,0 +1,388 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use glob::glob;
+
+use crate::{
+    build::{build, out_dir, output_path, static_lib_path, ArtifactKind, BuildInfo, BuildOptions},
+    error::{FatalError, FatalResult},
+    export::export_header_path,
+    progress,
+    project::{parse_spork_file, ProjectType},
+    success,
+    targets::OperatingSystem,
+    util::{mkfile, symlink},
+    warning, SPORK_FILE_NAME,
+};
+
+/// Files and directories created by an install, relative to the install root.
+#[derive(Default, Debug, PartialEq)]
+struct Manifest {
+    files: Vec<String>,
+    dirs: Vec<String>,
+}
+
+impl Manifest {
+    fn mkdir(&mut self, root: &str, dir: &str) -> FatalResult<()> {
+        let mut current = String::new();
+
+        for component in dir.split('/') {
+            if !current.is_empty() {
+                current.push('/');
+            }
+            current.push_str(component);
+
+            let path = format!("{root}/{current}");
+            if Path::new(&path).is_dir() {
+                continue;
+            }
+
+            if let Err(err) = fs::create_dir_all(&path) {
+                return Err(FatalError::CannotCreateDir { path, err });
+            }
+            self.dirs.push(current.clone());
+        }
+
+        Ok(())
+    }
+
+    fn copy(&mut self, root: &str, src_path: &str, dir: &str) -> FatalResult<()> {
+        let file_name = match Path::new(src_path).file_name() {
+            Some(res) => res.to_string_lossy().to_string(),
+            None => {
+                return Err(FatalError::FileInvalidUTF8 {
+                    path: Path::new(src_path).into(),
+                })
+            }
+        };
+
+        self.mkdir(root, dir)?;
+
+        let file = format!("{dir}/{file_name}");
+        let dst_path = format!("{root}/{file}");
+
+        // Symlinks between versions of a shared library are kept as they are
+        if let Ok(target) = fs::read_link(src_path) {
+            symlink(&target.to_string_lossy(), &dst_path)?;
+            self.files.push(file);
+            return Ok(());
+        }
+
+        if let Err(err) = fs::copy(src_path, &dst_path) {
+            return Err(FatalError::CannotCopyFile {
+                path: dst_path,
+                err,
+            });
+        }
+        self.files.push(file);
+
+        Ok(())
+    }
+
+    fn parse(src: &str) -> Self {
+        let mut manifest = Self::default();
+
+        for line in src.lines() {
+            if let Some(file) = line.strip_prefix("file ") {
+                manifest.files.push(file.to_string());
+            } else if let Some(dir) = line.strip_prefix("dir ") {
+                manifest.dirs.push(dir.to_string());
+            }
+        }
+
+        manifest
+    }
+
+    fn serialize(&self) -> String {
+        let mut src = String::new();
+
+        for dir in &self.dirs {
+            src.push_str(&format!("dir {dir}\n"));
+        }
+        for file in &self.files {
+            src.push_str(&format!("file {file}\n"));
+        }
+
+        src
+    }
+}
+
+pub fn install(prefix: &str, destdir: Option<&str>) -> FatalResult<()> {
+    let infos = build(&BuildOptions {
+        release: true,
+        ..Default::default()
+    })?;
+    let root = install_root(prefix, destdir);
+
+    let mut manifest = Manifest::default();
+    manifest.mkdir(&root, "share/spork")?;
+
+    for info in &infos {
+        progress!("installing '{}' to '{root}'...", info.name);
+
+        for (src_path, dir) in install_files(info)? {
+            manifest.copy(&root, &src_path, &dir)?;
+        }
+    }
+
+    let name = parse_spork_file(SPORK_FILE_NAME)?.project.name;
+    mkfile(&manifest_path(&root, &name), &manifest.serialize())?;
+
+    success!("installed {} files to '{root}'", manifest.files.len());
+
+    Ok(())
+}
+
+/// The files to install for a build, as pairs of source path and destination directory.
+pub fn install_files(info: &BuildInfo) -> FatalResult<Vec<(String, String)>> {
+    let mut files = Vec::new();
+
+    for artifact in &info.outputs {
+        match artifact.kind {
+            ArtifactKind::Executable => files.push((artifact.path.clone(), String::from("bin"))),
+            ArtifactKind::Library => {
+                for path in shared_lib_files(&artifact.path) {
+                    files.push((path, lib_dir(info).to_string()));
+                }
+                files.push((
+                    static_lib_path(&out_dir(info), &artifact.name),
+                    String::from("lib"),
+                ));
+
+                if info.target.os == OperatingSystem::Windows {
+                    let import_lib_path = artifact.path.replace(".dll", ".lib");
+                    files.push((import_lib_path, String::from("lib")));
+                }
+            }
+            ArtifactKind::Example | ArtifactKind::Test | ArtifactKind::Bench => {}
+        }
+    }
+
+    // Executables need their shared library dependencies at runtime
+    if let Some(deps) = &info.dependencies {
+        for name in deps.library_names() {
+            let lib_path = output_path(&out_dir(info), &name, ProjectType::library, &info.target);
+            for path in shared_lib_files(&lib_path) {
+                files.push((path, lib_dir(info).to_string()));
+            }
+        }
+    }
+
+    if info.kind == ProjectType::library {
+        files.push((export_header_path(info), format!("include/{}", info.name)));
+    }
+
+    if info.kind == ProjectType::library && Path::new("include").is_dir() {
+        for path in glob("include/**/*").unwrap().flatten() {
+            if !path.is_file() {
+                continue;
+            }
+
+            let (Some(path), Some(dir)) = (
+                path.to_str(),
+                path.parent().and_then(|parent| parent.to_str()),
+            ) else {
+                return Err(FatalError::FileInvalidUTF8 {
+                    path: path.as_path().into(),
+                });
+            };
+
+            files.push((path.to_string(), dir.replace('\\', "/")));
+        }
+    }
+
+    Ok(files)
+}
+
+/// A shared library, followed by the files its symlinks lead to - e.g. `libfoo.so`, `libfoo.so.1`
+/// and `libfoo.so.1.2.3` for a versioned library.
+fn shared_lib_files(path: &str) -> Vec<String> {
+    let mut files = vec![path.to_string()];
+    let mut current = PathBuf::from(path);
+
+    while let Ok(target) = fs::read_link(&current) {
+        current = current.with_file_name(target);
+        files.push(current.to_string_lossy().to_string());
+    }
+
+    files
+}
+
+/// Shared libraries go to `lib`, except for DLLs - Windows only finds those next to the executable.
+fn lib_dir(info: &BuildInfo) -> &'static str {
+    if info.target.os == OperatingSystem::Windows {
+        "bin"
+    } else {
+        "lib"
+    }
+}
+
+pub fn uninstall(prefix: &str, destdir: Option<&str>) -> FatalResult<()> {
+    let name = parse_spork_file(SPORK_FILE_NAME)?.project.name;
+    let root = install_root(prefix, destdir);
+
+    let manifest_path = manifest_path(&root, &name);
+    let manifest = match fs::read_to_string(&manifest_path) {
+        Ok(res) => Manifest::parse(&res),
+        Err(_) => {
+            return Err(FatalError::NoInstallManifest {
+                name,
+                path: manifest_path,
+            })
+        }
+    };
+
+    for file in manifest
+        .files
+        .iter()
+        .map(|file| format!("{root}/{file}"))
+        .chain([manifest_path])
+    {
+        if let Err(err) = fs::remove_file(&file) {
+            warning!("couldn't remove file '{file}': {err}");
+        }
+    }
+
+    // Only directories created by the install are removed, and only once they're empty
+    for dir in manifest.dirs.iter().rev() {
+        let _ = fs::remove_dir(format!("{root}/{dir}"));
+    }
+
+    success!("uninstalled '{name}' from '{root}'");
+
+    Ok(())
+}
+
+fn install_root(prefix: &str, destdir: Option<&str>) -> String {
+    let prefix = prefix.trim_end_matches('/');
+
+    match destdir {
+        Some(destdir) => format!(
+            "{}/{}",
+            destdir.trim_end_matches('/'),
+            prefix.trim_start_matches('/')
+        ),
+        None => prefix.to_string(),
+    }
+}
+
+fn manifest_path(root: &str, name: &str) -> String {
+    format!("{root}/share/spork/{name}.manifest")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    /// A fresh, empty directory to install into.
+    fn temp_root(name: &str) -> String {
+        let dir = env::temp_dir().join(format!("spork-install-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.to_string_lossy().replace('\\', "/")
+    }
+
+    #[test]
+    fn manifests_round_trip() {
+        let manifest = Manifest {
+            files: vec![
+                String::from("bin/app"),
+                String::from("lib/libfoo.so.1"),
+                String::from("include/foo/my file.h"),
+            ],
+            dirs: vec![String::from("share"), String::from("share/spork")],
+        };
+
+        let src = manifest.serialize();
+        assert_eq!(
+            src,
+            "dir share\ndir share/spork\nfile bin/app\nfile lib/libfoo.so.1\nfile include/foo/my file.h\n"
+        );
+        assert_eq!(Manifest::parse(&src), manifest);
+    }
+
+    #[test]
+    fn manifests_ignore_unknown_lines() {
+        let manifest = Manifest::parse("dir lib\n\nsymlink lib/x\nfile lib/libfoo.a\n");
+
+        assert_eq!(manifest.dirs, ["lib"]);
+        assert_eq!(manifest.files, ["lib/libfoo.a"]);
+    }
+
+    #[test]
+    fn only_created_dirs_are_recorded() {
+        let root = temp_root("mkdir");
+        fs::create_dir_all(format!("{root}/share")).unwrap();
+
+        let mut manifest = Manifest::default();
+        manifest.mkdir(&root, "share/spork").unwrap();
+        manifest.mkdir(&root, "share/spork").unwrap();
+
+        assert_eq!(manifest.dirs, ["share/spork"]);
+        assert!(Path::new(&format!("{root}/share/spork")).is_dir());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn copies_are_recorded() {
+        let root = temp_root("copy");
+        let src_path = format!("{root}/libfoo.a");
+        fs::write(&src_path, "archive").unwrap();
+
+        let mut manifest = Manifest::default();
+        manifest.copy(&root, &src_path, "usr/lib").unwrap();
+
+        assert_eq!(manifest.files, ["usr/lib/libfoo.a"]);
+        assert_eq!(manifest.dirs, ["usr", "usr/lib"]);
+        assert_eq!(
+            fs::read_to_string(format!("{root}/usr/lib/libfoo.a")).unwrap(),
+            "archive"
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn versioned_libraries_keep_their_symlinks() {
+        let root = temp_root("symlinks");
+        fs::write(format!("{root}/libfoo.so.1.2.3"), "").unwrap();
+        symlink("libfoo.so.1.2.3", &format!("{root}/libfoo.so.1")).unwrap();
+        symlink("libfoo.so.1", &format!("{root}/libfoo.so")).unwrap();
+
+        let files = shared_lib_files(&format!("{root}/libfoo.so"));
+        assert_eq!(
+            files,
+            [
+                format!("{root}/libfoo.so"),
+                format!("{root}/libfoo.so.1"),
+                format!("{root}/libfoo.so.1.2.3"),
+            ]
+        );
+
+        let mut manifest = Manifest::default();
+        for file in &files {
+            manifest.copy(&root, file, "lib").unwrap();
+        }
+
+        let link = fs::read_link(format!("{root}/lib/libfoo.so")).unwrap();
+        assert_eq!(link, Path::new("libfoo.so.1"));
+        assert!(fs::read_link(format!("{root}/lib/libfoo.so.1.2.3")).is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn destdir_prefixes_the_install_root() {
+        assert_eq!(install_root("/usr/local/", None), "/usr/local");
+        assert_eq!(
+            install_root("/usr/local", Some("/tmp/stage/")),
+            "/tmp/stage/usr/local"
+        );
+    }
+}
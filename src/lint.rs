@@ -0,0 +1,264 @@
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use regex::Regex;
+
+use crate::{
+    build::{build, compile_args, out_dir, BuildInfo, BuildOptions},
+    error::{FatalError, FatalResult},
+    message::{emit, is_json, Message},
+    progress,
+    project::parse_spork_file,
+    sources::collect_sources,
+    success,
+    util::{launch_dir, tool_cmd},
+    warning, SPORK_FILE_NAME,
+};
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Diagnostic {
+    file: String,
+    line: u32,
+    column: u32,
+    severity: String,
+    message: String,
+    check: String,
+}
+
+struct LintJob<'a> {
+    tool: &'static str,
+    file: &'a str,
+    cmd: Command,
+}
+
+/// Runs `clang-tidy` and/or the Clang static analyzer over every translation unit of the project,
+/// using the same flags the project is compiled with.
+///
+/// The project is built first, so that generated files and dependencies are in place.
+pub fn lint(analyze: bool, jobs: Option<usize>) -> FatalResult<()> {
+    let config = parse_spork_file(SPORK_FILE_NAME)?.lint.unwrap_or_default();
+    let clang_tidy = config.clang_tidy.unwrap_or(true);
+    let analyze = analyze || config.analyze.unwrap_or(false);
+
+    let infos = build(&BuildOptions::default())?;
+
+    let mut jobs_to_run = Vec::new();
+    let mut sources = Vec::new();
+    for info in &infos {
+        sources.push((info, lint_sources(info)?));
+    }
+
+    for (info, files) in &sources {
+        let args = compile_args(info)?;
+
+        for file in files {
+            if clang_tidy {
+                let mut cmd = tool_cmd("CLANG_TIDY", "clang-tidy");
+                cmd.arg("--quiet");
+                if let Some(checks) = &config.checks {
+                    cmd.arg(format!("--checks={}", checks.join(",")));
+                }
+                cmd.args([file.as_str(), "--"]).args(&args);
+
+                jobs_to_run.push(LintJob {
+                    tool: "clang-tidy",
+                    file,
+                    cmd,
+                });
+            }
+
+            if analyze {
+                let mut cmd = tool_cmd("CLANG", "clang");
+                cmd.args(["--analyze", "--analyzer-output", "text"])
+                    .args(&args)
+                    .arg(file);
+
+                jobs_to_run.push(LintJob {
+                    tool: "clang",
+                    file,
+                    cmd,
+                });
+            }
+        }
+    }
+
+    if jobs_to_run.is_empty() {
+        warning!("nothing to lint");
+        return Ok(());
+    }
+
+    let jobs = jobs
+        .or_else(|| thread::available_parallelism().ok().map(usize::from))
+        .unwrap_or(1)
+        .max(1);
+    progress!(
+        "linting {} files...",
+        sources.iter().map(|(_, files)| files.len()).sum::<usize>()
+    );
+
+    let mut diagnostics = run_jobs(jobs_to_run, jobs)?;
+    diagnostics.sort();
+    diagnostics.dedup();
+
+    if diagnostics.is_empty() {
+        success!("no lint issues found");
+        return Ok(());
+    }
+
+    if is_json() {
+        emit_lint_diagnostics(&diagnostics);
+    } else {
+        print_summary(&diagnostics);
+    }
+
+    Err(FatalError::LintIssues {
+        count: diagnostics.len(),
+    })
+}
+
+/// The project's own sources - generated sources such as embedded resources are left out.
+fn lint_sources(info: &BuildInfo) -> FatalResult<Vec<String>> {
+    let out_dir = out_dir(info);
+
+    Ok(collect_sources(&info.config, &info.target)?
+        .into_iter()
+        .filter(|file| !file.starts_with(&out_dir))
+        .collect())
+}
+
+fn run_jobs(jobs_to_run: Vec<LintJob>, jobs: usize) -> FatalResult<Vec<Diagnostic>> {
+    let diagnostic_regex =
+        Regex::new(r"^(.+?):(\d+):(\d+): (warning|error): (.*?)(?: \[([^\]]+)\])?$").unwrap();
+
+    let queue: Vec<_> = jobs_to_run.into_iter().map(Mutex::new).collect();
+    let next_job = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::new());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(queue.len()) {
+            scope.spawn(|| loop {
+                let index = next_job.fetch_add(1, Ordering::Relaxed);
+                let Some(job) = queue.get(index) else {
+                    break;
+                };
+
+                let mut job = job.lock().unwrap();
+                let result = run_job(&mut job, &diagnostic_regex);
+                results.lock().unwrap().push(result);
+            });
+        }
+    });
+
+    let mut diagnostics = Vec::new();
+    for result in results.into_inner().unwrap() {
+        diagnostics.extend(result?);
+    }
+
+    Ok(diagnostics)
+}
+
+fn run_job(job: &mut LintJob, diagnostic_regex: &Regex) -> FatalResult<Vec<Diagnostic>> {
+    let output = match job.cmd.output() {
+        Ok(res) => res,
+        Err(err) => {
+            return Err(FatalError::FailedRunTool {
+                tool: job.tool.to_string(),
+                err,
+            })
+        }
+    };
+
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let prefix = format!("{}/", launch_dir());
+    let diagnostics: Vec<_> = text
+        .lines()
+        .filter_map(|line| diagnostic_regex.captures(line))
+        .map(|captures| Diagnostic {
+            file: captures[1].trim_start_matches(&prefix).to_string(),
+            line: captures[2].parse().unwrap_or_default(),
+            column: captures[3].parse().unwrap_or_default(),
+            severity: captures[4].to_string(),
+            message: captures[5].to_string(),
+            check: captures.get(6).map_or_else(
+                || String::from("clang-diagnostic"),
+                |check| check.as_str().to_string(),
+            ),
+        })
+        .collect();
+
+    // A failure without any diagnostics means the tool itself went wrong, e.g. bad arguments
+    if !output.status.success() && diagnostics.is_empty() {
+        warning!(
+            "'{}' failed on '{}':\n{}",
+            job.tool,
+            job.file,
+            text.trim_end()
+        );
+        return Err(FatalError::ToolFailed {
+            tool: job.tool.to_string(),
+        });
+    }
+
+    Ok(diagnostics)
+}
+
+fn emit_lint_diagnostics(diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        emit(&Message::Diagnostic {
+            file: Some(&diagnostic.file),
+            line: Some(diagnostic.line),
+            column: Some(diagnostic.column),
+            severity: &diagnostic.severity,
+            message: &format!("{} [{}]", diagnostic.message, diagnostic.check),
+        });
+    }
+}
+
+fn print_summary(diagnostics: &[Diagnostic]) {
+    let mut by_file: BTreeMap<&str, Vec<&Diagnostic>> = BTreeMap::new();
+    let mut by_check: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for diagnostic in diagnostics {
+        by_file
+            .entry(&diagnostic.file)
+            .or_default()
+            .push(diagnostic);
+        *by_check.entry(&diagnostic.check).or_default() += 1;
+    }
+
+    for (file, diagnostics) in by_file {
+        warning!("{file}: {} issues", diagnostics.len());
+
+        for diagnostic in diagnostics {
+            println!(
+                "    {}:{}: {}: {} [{}]",
+                diagnostic.line,
+                diagnostic.column,
+                diagnostic.severity,
+                diagnostic.message,
+                diagnostic.check
+            );
+        }
+    }
+
+    let mut by_check: Vec<_> = by_check.into_iter().collect();
+    by_check.sort_by_key(|(_, count)| Reverse(*count));
+
+    progress!("issues by check:");
+    for (check, count) in by_check {
+        println!("    {check}: {count}");
+    }
+}
@@ -1,44 +1,88 @@
+mod add;
+mod bench;
 mod build;
+mod build_info;
+mod build_script;
+mod cfg;
+mod coverage;
+mod doctor;
 mod error;
+mod export;
+mod fmt;
+mod hooks;
 mod init;
+mod install;
+mod lint;
+mod message;
+mod metadata;
+mod package;
 mod project;
+mod resources;
+mod sanitize;
+mod sources;
 mod targets;
+mod test;
+mod toolchain;
+mod tree;
 mod util;
+mod version_info;
+mod watch;
 
 use std::{env::current_dir, fs, process::exit};
 
+use build::BuildOptions;
 use clap::{Parser, Subcommand};
 use error::{FatalError, FatalResult};
+use init::MetadataArgs;
+use message::{set_message_format, MessageFormat};
 use project::{parse_spork_file, ProjectType};
+use tree::TreeFormat;
 use util::update_launch_dir;
+use watch::WatchCommand;
 
 const SPORK_FILE_NAME: &str = "Spork.toml";
 
 #[macro_export]
 macro_rules! success {
     ($($arg:tt)+) => {{
-        println!("{} {}", yansi::Paint::green("[*]").bold(), format!($($arg)+))
+        $crate::message::print_text(
+            "success",
+            yansi::Paint::green("[*]").bold(),
+            &format!($($arg)+),
+        )
     }};
 }
 
 #[macro_export]
 macro_rules! progress {
     ($($arg:tt)+) => {{
-        println!("{} {}", yansi::Paint::blue("[+]").bold(), format!($($arg)+))
+        $crate::message::print_text(
+            "progress",
+            yansi::Paint::blue("[+]").bold(),
+            &format!($($arg)+),
+        )
     }};
 }
 
 #[macro_export]
 macro_rules! warning {
     ($($arg:tt)+) => {{
-        println!("{} {}", yansi::Paint::yellow("[?]").bold(), format!($($arg)+))
+        $crate::message::print_text(
+            "warning",
+            yansi::Paint::yellow("[?]").bold(),
+            &format!($($arg)+),
+        )
     }};
 }
 
 #[macro_export]
 macro_rules! fatal_error {
     ($($arg:tt)+) => {{
-        println!("{} {}", yansi::Paint::red("[!]").bold(), format!($($arg)+))
+        $crate::message::print_text(
+            "error",
+            yansi::Paint::red("[!]").bold(),
+            &format!($($arg)+),
+        )
     }};
 }
 
@@ -47,6 +91,10 @@ macro_rules! fatal_error {
 struct Cli {
     #[command(subcommand)]
     cmd: Commands,
+
+    /// Output format for messages - 'json' prints one JSON object per build event
+    #[arg(long, global = true, value_enum, default_value = "human")]
+    message_format: MessageFormat,
 }
 
 #[derive(Subcommand)]
@@ -63,6 +111,9 @@ enum Commands {
         /// Create project even if directory already contains files
         #[arg(short, long)]
         force: bool,
+
+        #[command(flatten)]
+        metadata: MetadataArgs,
     },
 
     /// Create a new spork project in the current directory
@@ -74,6 +125,9 @@ enum Commands {
         /// Create project even if directory already contains files
         #[arg(short, long)]
         force: bool,
+
+        #[command(flatten)]
+        metadata: MetadataArgs,
     },
 
     /// Build the current project
@@ -85,6 +139,25 @@ enum Commands {
         /// Build for all targets
         #[arg(short, long)]
         all: bool,
+
+        /// Also build the examples in 'examples'
+        #[arg(long)]
+        examples: bool,
+
+        /// Comma-separated list of sanitizers to enable (address, undefined, thread)
+        #[arg(long, value_delimiter = ',')]
+        sanitize: Option<Vec<String>>,
+    },
+
+    /// Compile the current project without linking, to check for errors quickly
+    Check {
+        /// Check in release mode instead of debug
+        #[arg(short, long)]
+        release: bool,
+
+        /// Check for all targets
+        #[arg(short, long)]
+        all: bool,
     },
 
     /// Build and run the current project
@@ -96,6 +169,146 @@ enum Commands {
         /// Build for all targets
         #[arg(short, long)]
         all: bool,
+
+        /// Name of the executable to run
+        #[arg(long)]
+        bin: Option<String>,
+
+        /// Name of the example to run
+        #[arg(long, conflicts_with = "bin")]
+        example: Option<String>,
+
+        /// Comma-separated list of sanitizers to enable (address, undefined, thread)
+        #[arg(long, value_delimiter = ',')]
+        sanitize: Option<Vec<String>>,
+    },
+
+    /// Build and run the tests in 'tests'
+    Test {
+        /// Build in release mode instead of debug
+        #[arg(short, long)]
+        release: bool,
+
+        /// Comma-separated list of sanitizers to enable (address, undefined, thread)
+        #[arg(long, value_delimiter = ',')]
+        sanitize: Option<Vec<String>>,
+
+        /// Generate a coverage report in 'bin/<target>/coverage'
+        #[arg(long)]
+        coverage: bool,
+    },
+
+    /// Build and run the benchmarks in 'benches' in release mode
+    Bench {
+        /// Only run benchmarks whose name contains this
+        filter: Option<String>,
+
+        /// Save the results as the baseline that later runs are compared to
+        #[arg(long)]
+        save_baseline: bool,
+
+        /// Compare against (and save to) a named baseline instead of the default one
+        #[arg(long, value_name = "NAME")]
+        baseline: Option<String>,
+    },
+
+    /// Build in release mode and install the project's files into a prefix
+    Install {
+        /// Directory to install into
+        #[arg(long, default_value = "/usr/local")]
+        prefix: String,
+
+        /// Directory to stage the install in, prepended to the prefix
+        #[arg(long)]
+        destdir: Option<String>,
+    },
+
+    /// Remove the files installed by 'spork install'
+    Uninstall {
+        /// Directory the project was installed into
+        #[arg(long, default_value = "/usr/local")]
+        prefix: String,
+
+        /// Directory the install was staged in
+        #[arg(long)]
+        destdir: Option<String>,
+    },
+
+    /// Build in release mode and bundle the outputs into archives in 'dist'
+    Package {
+        /// Package for all targets
+        #[arg(short, long)]
+        all: bool,
+    },
+
+    /// Format the project's C files with clang-format
+    Fmt {
+        /// List unformatted files and fail instead of formatting them
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Check the project's sources with clang-tidy and the Clang static analyzer
+    Lint {
+        /// Also run the Clang static analyzer
+        #[arg(long)]
+        analyze: bool,
+
+        /// Number of files to check in parallel (defaults to the number of CPUs)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+
+    /// Add a library to the project's dependencies
+    Add {
+        /// Path to a spork library, or a git URL to clone into 'deps'
+        source: String,
+
+        /// Features of the library to enable, separated by commas
+        #[arg(long, value_delimiter = ',')]
+        features: Vec<String>,
+    },
+
+    /// Remove a library from the project's dependencies
+    Remove {
+        /// Name (or path) of the dependency
+        name: String,
+    },
+
+    /// Print the project's metadata and dependencies as JSON
+    Metadata,
+
+    /// Print the project's dependency graph
+    Tree {
+        /// Target to resolve dependencies for (defaults to the project's first target)
+        #[arg(long)]
+        target: Option<String>,
+
+        /// Show the projects that depend on this library (a name or path) instead
+        #[arg(short, long, value_name = "LIBRARY")]
+        invert: Option<String>,
+
+        /// Only show libraries that are pulled in from more than one directory
+        #[arg(short, long, conflicts_with = "invert")]
+        duplicates: bool,
+
+        /// Output format - 'dot' prints a Graphviz graph
+        #[arg(long, value_enum, default_value = "text")]
+        format: TreeFormat,
+    },
+
+    /// Check that spork's toolchain and tools are installed, and that the project file is valid
+    Doctor,
+
+    /// Run a command again whenever the project's files change
+    Watch {
+        /// Command to run
+        #[arg(value_enum, default_value = "build")]
+        command: WatchCommand,
+
+        /// Build in release mode instead of debug
+        #[arg(short, long)]
+        release: bool,
     },
 
     /// Removes the 'bin' directory
@@ -105,25 +318,104 @@ enum Commands {
 fn main() {
     update_launch_dir();
     if let Err(err) = init() {
-        fatal_error!("{err}");
+        message::report_error(&err);
         exit(1);
     }
 }
 
 fn init() -> FatalResult<()> {
     let cli = Cli::parse();
+    set_message_format(cli.message_format);
 
     match cli.cmd {
-        Commands::New { name, lib, force } => new_project(&name, lib, force),
-        Commands::Init { lib, force } => init_project(lib, force),
-
-        Commands::Build { release, all } => build_project(release, all),
-        Commands::Run { release, all } => run_project(release, all),
+        Commands::New {
+            name,
+            lib,
+            force,
+            metadata,
+        } => new_project(&name, lib, force, metadata),
+        Commands::Init {
+            lib,
+            force,
+            metadata,
+        } => init_project(lib, force, metadata),
+
+        Commands::Build {
+            release,
+            all,
+            examples,
+            sanitize,
+        } => build_project(BuildOptions {
+            release,
+            all,
+            examples,
+            sanitize,
+            ..Default::default()
+        }),
+        Commands::Check { release, all } => build_project(BuildOptions {
+            release,
+            all,
+            check: true,
+            ..Default::default()
+        }),
+        Commands::Run {
+            release,
+            all,
+            bin,
+            example,
+            sanitize,
+        } => run_project(
+            BuildOptions {
+                release,
+                all,
+                sanitize,
+                ..Default::default()
+            },
+            bin,
+            example,
+        ),
+        Commands::Test {
+            release,
+            sanitize,
+            coverage,
+        } => test::test(&BuildOptions {
+            release,
+            sanitize,
+            coverage,
+            ..Default::default()
+        }),
+        Commands::Bench {
+            filter,
+            save_baseline,
+            baseline,
+        } => bench::bench(filter.as_deref(), save_baseline, baseline.as_deref()),
+        Commands::Install { prefix, destdir } => install::install(&prefix, destdir.as_deref()),
+        Commands::Uninstall { prefix, destdir } => install::uninstall(&prefix, destdir.as_deref()),
+        Commands::Package { all } => package::package(all),
+        Commands::Fmt { check } => fmt::fmt(check),
+        Commands::Lint { analyze, jobs } => lint::lint(analyze, jobs),
+        Commands::Add { source, features } => add::add(&source, &features),
+        Commands::Remove { name } => add::remove(&name),
+        Commands::Metadata => metadata::metadata(),
+        Commands::Tree {
+            target,
+            invert,
+            duplicates,
+            format,
+        } => tree::tree(target.as_deref(), invert.as_deref(), duplicates, format),
+        Commands::Doctor => doctor::doctor(),
+        Commands::Watch { command, release } => watch::watch(
+            command,
+            &BuildOptions {
+                release,
+                ..Default::default()
+            },
+        ),
         Commands::Clean => clean_project(),
     }
 }
 
-fn new_project(name: &str, lib: bool, force: bool) -> FatalResult<()> {
+fn new_project(name: &str, lib: bool, force: bool, metadata: MetadataArgs) -> FatalResult<()> {
     let project_type = if lib {
         ProjectType::library
     } else {
@@ -137,12 +429,12 @@ fn new_project(name: &str, lib: bool, force: bool) -> FatalResult<()> {
         }
     }
 
-    init::new_project(name, name, project_type)?;
+    init::new_project(name, name, project_type, metadata)?;
 
     Ok(())
 }
 
-fn init_project(lib: bool, force: bool) -> FatalResult<()> {
+fn init_project(lib: bool, force: bool, metadata: MetadataArgs) -> FatalResult<()> {
     let project_type = if lib {
         ProjectType::library
     } else {
@@ -154,7 +446,7 @@ fn init_project(lib: bool, force: bool) -> FatalResult<()> {
         Err(err) => return Err(FatalError::CannotGetCurrentDir { err }),
     };
 
-    let project_name = match current_dir.iter().last() {
+    let project_name = match current_dir.iter().next_back() {
         Some(res) => match res.to_str() {
             Some(res) => res,
             None => return Err(FatalError::CurrentDirInvalidUTF8),
@@ -183,18 +475,22 @@ fn init_project(lib: bool, force: bool) -> FatalResult<()> {
         }
     }
 
-    init::new_project(project_name, project_path, project_type)?;
+    init::new_project(project_name, project_path, project_type, metadata)?;
 
     Ok(())
 }
 
-fn build_project(release: bool, all: bool) -> FatalResult<()> {
-    build::build(release, all)?;
+fn build_project(options: BuildOptions) -> FatalResult<()> {
+    build::build(&options)?;
     Ok(())
 }
 
-fn run_project(release: bool, all: bool) -> FatalResult<()> {
-    build::build_and_run(release, all)?;
+fn run_project(
+    options: BuildOptions,
+    bin: Option<String>,
+    example: Option<String>,
+) -> FatalResult<()> {
+    build::build_and_run(&options, bin, example)?;
     Ok(())
 }
 
@@ -0,0 +1,122 @@
+use std::{fmt::Display, io, process::Command, sync::OnceLock};
+
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{build::ArtifactKind, error::FatalError, fatal_error};
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MessageFormat {
+    Human,
+    Json,
+}
+
+static MESSAGE_FORMAT: OnceLock<MessageFormat> = OnceLock::new();
+
+pub fn set_message_format(format: MessageFormat) {
+    MESSAGE_FORMAT.get_or_init(|| format);
+}
+
+pub fn is_json() -> bool {
+    MESSAGE_FORMAT.get() == Some(&MessageFormat::Json)
+}
+
+/// A build event, printed as one line of JSON with `--message-format json`.
+#[derive(Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum Message<'a> {
+    BuildStarted {
+        name: &'a str,
+        target: String,
+        profile: &'a str,
+    },
+    Diagnostic {
+        file: Option<&'a str>,
+        line: Option<u32>,
+        column: Option<u32>,
+        severity: &'a str,
+        message: &'a str,
+    },
+    Artifact {
+        name: &'a str,
+        kind: ArtifactKind,
+        path: &'a str,
+    },
+    BuildFinished {
+        name: &'a str,
+        target: String,
+        profile: &'a str,
+        duration: f64,
+    },
+    Error {
+        variant: &'static str,
+        message: String,
+    },
+    Text {
+        level: &'a str,
+        message: &'a str,
+    },
+}
+
+pub fn emit(message: &Message) {
+    println!("{}", serde_json::to_string(message).unwrap());
+}
+
+/// Sends a child process's stdout to stderr with `--message-format json`, keeping stdout free for
+/// JSON messages.
+pub fn redirect_stdout(cmd: &mut Command) {
+    if is_json() {
+        cmd.stdout(io::stderr());
+    }
+}
+
+/// Prints the output of the `success!`, `progress!`, `warning!` and `fatal_error!` macros.
+pub fn print_text(level: &str, prefix: impl Display, text: &str) {
+    if is_json() {
+        emit(&Message::Text {
+            level,
+            message: text,
+        });
+    } else {
+        println!("{prefix} {text}");
+    }
+}
+
+pub fn report_error(err: &FatalError) {
+    if is_json() {
+        emit(&Message::Error {
+            variant: err.name(),
+            message: err.to_string(),
+        });
+    } else {
+        fatal_error!("{err}");
+    }
+}
+
+/// Emits the diagnostics in the output of `zig cc`, such as `src/main.c:3:5: error: ...` or
+/// `error: undefined symbol: foo` from the linker.
+pub fn emit_diagnostics(output: &str) {
+    let located_regex = Regex::new(r"^(.+?):(\d+):(\d+): (error|warning|note): (.*)$").unwrap();
+    let unlocated_regex = Regex::new(r"^(error|warning|note): (.*)$").unwrap();
+
+    for line in output.lines() {
+        if let Some(captures) = located_regex.captures(line) {
+            emit(&Message::Diagnostic {
+                file: Some(&captures[1]),
+                line: captures[2].parse().ok(),
+                column: captures[3].parse().ok(),
+                severity: &captures[4],
+                message: &captures[5],
+            });
+        } else if let Some(captures) = unlocated_regex.captures(line) {
+            emit(&Message::Diagnostic {
+                file: None,
+                line: None,
+                column: None,
+                severity: &captures[1],
+                message: &captures[2],
+            });
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    build::{Dependencies, Dependency},
+    error::FatalResult,
+    project::{parse_spork_file, Metadata, ProjectType},
+    toolchain::set_toolchain,
+    util::canonical_key,
+    SPORK_FILE_NAME,
+};
+
+#[derive(Serialize)]
+struct ProjectMetadata {
+    name: String,
+    kind: ProjectType,
+    #[serde(flatten)]
+    metadata: Metadata,
+    targets: Option<Vec<String>>,
+    dependencies: Vec<DependencyMetadata>,
+}
+
+#[derive(Serialize)]
+struct DependencyMetadata {
+    name: String,
+    path: String,
+    #[serde(flatten)]
+    metadata: Metadata,
+}
+
+/// Prints the project's metadata and that of its dependencies (for its first target) as JSON.
+pub fn metadata() -> FatalResult<()> {
+    let spork_file = parse_spork_file(SPORK_FILE_NAME)?;
+    spork_file.project.metadata().validate()?;
+    set_toolchain(spork_file.toolchain.as_ref());
+
+    let target = spork_file.default_target()?;
+
+    // Dependencies are listed once each (per directory, as in 'spork tree'), including those of
+    // dependencies
+    let mut dependencies = BTreeMap::new();
+    if let Some(deps) = spork_file.target_config(&target)?.dependencies {
+        let deps = Dependencies::new(deps, &target, spork_file.dependency_features.as_ref())?;
+        collect_dependencies(deps.iter(), &mut dependencies);
+    }
+
+    let project = ProjectMetadata {
+        metadata: spork_file.project.metadata(),
+        name: spork_file.project.name,
+        kind: spork_file.project.kind,
+        targets: spork_file.project.targets,
+        dependencies: dependencies.into_values().collect(),
+    };
+
+    println!("{}", serde_json::to_string_pretty(&project).unwrap());
+
+    Ok(())
+}
+
+fn collect_dependencies<'a>(
+    deps: impl Iterator<Item = &'a Dependency>,
+    collected: &mut BTreeMap<String, DependencyMetadata>,
+) {
+    for dep in deps {
+        if let Some(deps) = &dep.deps {
+            collect_dependencies(deps.iter(), collected);
+        }
+
+        collected
+            .entry(canonical_key(&dep.path))
+            .or_insert_with(|| DependencyMetadata {
+                name: dep.name.clone(),
+                path: dep.path.clone(),
+                metadata: dep.metadata.clone(),
+            });
+    }
+}
@@ -0,0 +1,430 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
+
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
+use tar::{Builder, EntryType, Header};
+use zip::{write::FileOptions, CompressionMethod, DateTime, ZipWriter};
+
+use crate::{
+    build::{build, ArtifactKind, BuildOptions},
+    error::{FatalError, FatalResult},
+    install::install_files,
+    progress,
+    project::{parse_spork_file, ProjectType},
+    success,
+    targets::OperatingSystem,
+    util::mkdir_all,
+    SPORK_FILE_NAME,
+};
+
+// 1980-01-01, the earliest timestamp zip archives can store
+const ARCHIVE_MTIME: u64 = 315532800;
+
+const METADATA_FILE_NAME: &str = "metadata.toml";
+
+#[derive(Serialize)]
+struct PackageMetadata {
+    name: String,
+    version: String,
+    kind: ProjectType,
+    authors: Option<Vec<String>>,
+    description: Option<String>,
+    license: Option<String>,
+    repository: Option<String>,
+    target: String,
+    files: Vec<String>,
+}
+
+struct PackageFile {
+    path: String,
+    data: Vec<u8>,
+    executable: bool,
+    link: Option<String>,
+}
+
+pub fn package(all: bool) -> FatalResult<()> {
+    let spork_file = parse_spork_file(SPORK_FILE_NAME)?;
+    spork_file.project.metadata().validate()?;
+    let Some(version) = spork_file.project.version.clone() else {
+        return Err(FatalError::NoProjectVersion);
+    };
+
+    let infos = build(&BuildOptions {
+        release: true,
+        all,
+        ..Default::default()
+    })?;
+    mkdir_all("dist")?;
+
+    for info in infos {
+        let package_name = format!("{}-{version}-{}", info.name, info.target);
+        progress!("packaging '{package_name}'...");
+
+        let mut files = Vec::new();
+
+        let executables: Vec<_> = info
+            .outputs
+            .iter()
+            .filter(|artifact| artifact.kind == ArtifactKind::Executable)
+            .map(|artifact| artifact.path.as_str())
+            .collect();
+
+        for (src_path, dir) in install_files(&info)? {
+            let file_name = Path::new(&src_path).file_name().unwrap().to_string_lossy();
+            let path = format!("{package_name}/{dir}/{file_name}");
+
+            if let Ok(target) = fs::read_link(&src_path) {
+                files.push(PackageFile {
+                    path,
+                    data: Vec::new(),
+                    executable: false,
+                    link: Some(target.to_string_lossy().to_string()),
+                });
+                continue;
+            }
+
+            files.push(PackageFile {
+                path,
+                data: read_file(&src_path)?,
+                executable: executables.contains(&src_path.as_str()),
+                link: None,
+            });
+        }
+
+        for path in extra_files(spork_file.project.readme.as_deref())? {
+            let file_name = Path::new(&path).file_name().unwrap().to_string_lossy();
+            files.push(PackageFile {
+                path: format!("{package_name}/{file_name}"),
+                data: read_file(&path)?,
+                executable: false,
+                link: None,
+            });
+        }
+
+        let metadata = PackageMetadata {
+            name: info.name.clone(),
+            version: version.clone(),
+            kind: info.kind,
+            authors: spork_file.project.authors.clone(),
+            description: spork_file.project.description.clone(),
+            license: spork_file.project.license.clone(),
+            repository: spork_file.project.repository.clone(),
+            target: info.target.to_string(),
+            files: Vec::new(),
+        };
+        add_metadata(&package_name, &mut files, metadata);
+
+        let archive_path = if info.target.os == OperatingSystem::Windows {
+            format!("dist/{package_name}.zip")
+        } else {
+            format!("dist/{package_name}.tar.gz")
+        };
+
+        let write_res = if info.target.os == OperatingSystem::Windows {
+            write_zip(&archive_path, &files)
+        } else {
+            write_tar_gz(&archive_path, &files)
+        };
+
+        if let Err(err) = write_res {
+            return Err(FatalError::CannotCreateFile {
+                path: archive_path,
+                err,
+            });
+        }
+
+        success!("packaged '{archive_path}'");
+    }
+
+    Ok(())
+}
+
+/// License and readme files in the project directory, plus the project's `readme` wherever it is,
+/// in a stable order.
+fn extra_files(readme: Option<&str>) -> FatalResult<Vec<String>> {
+    let dir = match fs::read_dir(".") {
+        Ok(res) => res,
+        Err(err) => {
+            return Err(FatalError::CannotReadDir {
+                path: String::from("."),
+                err,
+            })
+        }
+    };
+
+    let mut files: Vec<_> = dir
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| entry.file_name().to_str().map(String::from))
+        .filter(|name| {
+            let name = name.to_uppercase();
+            ["LICENSE", "LICENCE", "COPYING", "README"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        })
+        .collect();
+
+    if let Some(readme) = readme {
+        // Archives are flat, so a readme named like a file that's already included is skipped
+        let file_name = Path::new(readme).file_name();
+        if !files
+            .iter()
+            .any(|file| Path::new(file).file_name() == file_name)
+        {
+            files.push(readme.to_string());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Sorts the files of a package, so that archives don't depend on the order they were found in,
+/// and appends the metadata file listing them.
+fn add_metadata(package_name: &str, files: &mut Vec<PackageFile>, mut metadata: PackageMetadata) {
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    metadata.files = files
+        .iter()
+        .map(|file| file.path[(package_name.len() + 1)..].to_string())
+        .collect();
+
+    files.push(PackageFile {
+        path: format!("{package_name}/{METADATA_FILE_NAME}"),
+        data: toml::to_string_pretty(&metadata).unwrap().into_bytes(),
+        executable: false,
+        link: None,
+    });
+}
+
+fn read_file(path: &str) -> FatalResult<Vec<u8>> {
+    match fs::read(path) {
+        Ok(res) => Ok(res),
+        Err(err) => Err(FatalError::CannotReadFile {
+            path: path.to_string(),
+            err,
+        }),
+    }
+}
+
+fn write_tar_gz(path: &str, files: &[PackageFile]) -> io::Result<()> {
+    let encoder = GzEncoder::new(File::create(path)?, Compression::default());
+    let mut builder = Builder::new(encoder);
+
+    for file in files {
+        let mut header = Header::new_gnu();
+        header.set_path(&file.path)?;
+        header.set_size(file.data.len() as u64);
+        header.set_mode(if file.executable { 0o755 } else { 0o644 });
+        header.set_mtime(ARCHIVE_MTIME);
+        header.set_uid(0);
+        header.set_gid(0);
+
+        if let Some(link) = &file.link {
+            header.set_entry_type(EntryType::Symlink);
+            header.set_mode(0o777);
+            header.set_link_name(link)?;
+        }
+
+        header.set_cksum();
+
+        builder.append(&header, file.data.as_slice())?;
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn write_zip(path: &str, files: &[PackageFile]) -> io::Result<()> {
+    let mut writer = ZipWriter::new(File::create(path)?);
+
+    for file in files {
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .last_modified_time(DateTime::default())
+            .unix_permissions(if file.executable { 0o755 } else { 0o644 });
+
+        if let Some(link) = &file.link {
+            writer.add_symlink(&file.path, link, options)?;
+            continue;
+        }
+
+        writer.start_file(&file.path, options)?;
+        writer.write_all(&file.data)?;
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use flate2::read::GzDecoder;
+    use tar::Archive;
+    use zip::ZipArchive;
+
+    use super::*;
+
+    fn file(path: &str, data: &str, executable: bool) -> PackageFile {
+        PackageFile {
+            path: path.to_string(),
+            data: data.as_bytes().to_vec(),
+            executable,
+            link: None,
+        }
+    }
+
+    fn files() -> Vec<PackageFile> {
+        vec![
+            file("pkg/bin/app", "binary", true),
+            PackageFile {
+                path: String::from("pkg/lib/libfoo.so"),
+                data: Vec::new(),
+                executable: false,
+                link: Some(String::from("libfoo.so.1")),
+            },
+            file("pkg/lib/libfoo.so.1", "library", false),
+        ]
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = env::temp_dir().join(format!("spork-package-{name}-{}", std::process::id()));
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn files_are_sorted_before_the_metadata() {
+        let mut files = vec![
+            file("pkg/lib/libfoo.a", "", false),
+            file("pkg/README.md", "", false),
+            file("pkg/bin/app", "", true),
+        ];
+        let metadata = PackageMetadata {
+            name: String::from("foo"),
+            version: String::from("1.0.0"),
+            kind: ProjectType::executable,
+            authors: None,
+            description: None,
+            license: None,
+            repository: None,
+            target: String::from("x86_64-linux"),
+            files: Vec::new(),
+        };
+
+        add_metadata("pkg", &mut files, metadata);
+
+        let paths: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "pkg/README.md",
+                "pkg/bin/app",
+                "pkg/lib/libfoo.a",
+                "pkg/metadata.toml"
+            ]
+        );
+
+        let metadata: toml::Value =
+            toml::from_str(std::str::from_utf8(&files[3].data).unwrap()).unwrap();
+        assert_eq!(
+            metadata["files"],
+            toml::Value::from(vec!["README.md", "bin/app", "lib/libfoo.a"])
+        );
+    }
+
+    #[test]
+    fn tar_archives_are_deterministic() {
+        let path = temp_path("a.tar.gz");
+        write_tar_gz(&path, &files()).unwrap();
+        let first = fs::read(&path).unwrap();
+        write_tar_gz(&path, &files()).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), first);
+
+        let mut archive = Archive::new(GzDecoder::new(first.as_slice()));
+        let entries: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let header = entry.unwrap().header().clone();
+                (
+                    header.path().unwrap().to_string_lossy().to_string(),
+                    header.mode().unwrap(),
+                    header.mtime().unwrap(),
+                    header.uid().unwrap(),
+                    header.gid().unwrap(),
+                    header.entry_type(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            entries,
+            [
+                (
+                    String::from("pkg/bin/app"),
+                    0o755,
+                    ARCHIVE_MTIME,
+                    0,
+                    0,
+                    EntryType::Regular
+                ),
+                (
+                    String::from("pkg/lib/libfoo.so"),
+                    0o777,
+                    ARCHIVE_MTIME,
+                    0,
+                    0,
+                    EntryType::Symlink
+                ),
+                (
+                    String::from("pkg/lib/libfoo.so.1"),
+                    0o644,
+                    ARCHIVE_MTIME,
+                    0,
+                    0,
+                    EntryType::Regular
+                ),
+            ]
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn zip_archives_are_deterministic() {
+        let path = temp_path("a.zip");
+        write_zip(&path, &files()).unwrap();
+        let first = fs::read(&path).unwrap();
+        write_zip(&path, &files()).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), first);
+
+        let mut archive = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut entries = Vec::new();
+        for i in 0..archive.len() {
+            let entry = archive.by_index(i).unwrap();
+            let modified = entry.last_modified();
+            entries.push((
+                entry.name().to_string(),
+                entry.unix_mode().unwrap() & 0o170777,
+                (modified.year(), modified.month(), modified.day()),
+            ));
+        }
+
+        assert_eq!(
+            entries,
+            [
+                (String::from("pkg/bin/app"), 0o100755, (1980, 1, 1)),
+                (String::from("pkg/lib/libfoo.so"), 0o120644, (1980, 1, 1)),
+                (String::from("pkg/lib/libfoo.so.1"), 0o100644, (1980, 1, 1)),
+            ]
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,20 +1,282 @@
-use std::{fmt::Display, fs};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    fs,
+};
 
+use semver::Version;
 use serde::{Deserialize, Serialize};
+use spdx::Expression;
 
-use crate::error::{FatalError, FatalResult};
+use crate::{
+    cfg::Cfg,
+    error::{FatalError, FatalResult},
+    sources::DEFAULT_SOURCES,
+    targets::Target,
+    toolchain::ToolchainKind,
+    warning,
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ProjectFile {
     pub project: ProjectInfo,
+    pub target: Option<HashMap<String, TargetTable>>,
+    pub bin: Option<Vec<BinInfo>>,
+    pub profile: Option<Profiles>,
+    pub hooks: Option<Hooks>,
+    pub resources: Option<BTreeMap<String, String>>,
+    pub lint: Option<Lint>,
+    pub toolchain: Option<ToolchainConfig>,
+    pub dependency_features: Option<HashMap<String, Vec<String>>>,
+}
+
+impl ProjectFile {
+    pub fn profile(&self, release: bool) -> Option<&Profile> {
+        let profiles = self.profile.as_ref()?;
+
+        if release {
+            profiles.release.as_ref()
+        } else {
+            profiles.debug.as_ref()
+        }
+    }
+
+    /// Warns if the project's metadata isn't valid. Only commands that publish it (`package` and
+    /// `metadata`) reject invalid metadata, so projects with e.g. a `"1.0"` version still build.
+    pub fn warn_invalid_metadata(&self) {
+        if let Err(err) = self.project.metadata().validate() {
+            warning!("'{}': {err}", self.project.name);
+        }
+    }
+
+    /// The target commands that work on a single target default to - the project's first
+    /// target, or the host.
+    pub fn default_target(&self) -> FatalResult<Target> {
+        match self.project.targets.as_deref() {
+            Some([target, ..]) => Target::new(target, false),
+            _ => Target::host(),
+        }
+    }
+
+    /// Resolves the project settings for a concrete target, extending them with every
+    /// `[target.<triple>]` and `[target.'cfg(...)']` table that matches it. A table's
+    /// `override` settings replace what the project and earlier tables set instead.
+    pub fn target_config(&self, target: &Target) -> FatalResult<TargetConfig> {
+        let mut config = TargetConfig {
+            sources: Some(
+                self.project
+                    .sources
+                    .clone()
+                    .unwrap_or_else(|| vec![DEFAULT_SOURCES.to_string()]),
+            ),
+            exclude: self.project.exclude.clone(),
+            dependencies: self.project.dependencies.clone(),
+            ..Default::default()
+        };
+
+        if let Some(tables) = &self.target {
+            // Sorted so that the order of flags doesn't depend on hashing
+            let mut specs: Vec<_> = tables.keys().collect();
+            specs.sort();
+
+            for spec in specs {
+                let matches = if spec.starts_with("cfg(") {
+                    Cfg::new(spec)?.matches(target)
+                } else {
+                    &Target::new(spec, false)? == target
+                };
+
+                if matches {
+                    let table = &tables[spec];
+                    config.extend(&table.config);
+
+                    if let Some(overrides) = &table.overrides {
+                        config.replace(overrides);
+                    }
+                }
+            }
+        }
+
+        Ok(config)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ProjectInfo {
     pub name: String,
+    pub version: Option<String>,
     pub kind: ProjectType,
+    pub authors: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub repository: Option<String>,
+    pub readme: Option<String>,
     pub targets: Option<Vec<String>>,
     pub dependencies: Option<Vec<String>>,
+    pub features: Option<Vec<String>>,
+    pub sources: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+}
+
+impl ProjectInfo {
+    /// Checks that the library offers every feature that a project enables for it.
+    pub fn check_features(&self, enabled: &[String]) -> FatalResult<()> {
+        let offered = self.features.as_deref().unwrap_or_default();
+
+        for feature in enabled {
+            if !offered.contains(feature) {
+                return Err(FatalError::NoSuchFeature {
+                    dep: self.name.clone(),
+                    feature: feature.clone(),
+                    offered: offered.to_vec(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        Metadata {
+            version: self.version.clone(),
+            authors: self.authors.clone(),
+            description: self.description.clone(),
+            license: self.license.clone(),
+            repository: self.repository.clone(),
+            readme: self.readme.clone(),
+        }
+    }
+}
+
+/// The fields describing a project, rather than how to build it.
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct Metadata {
+    pub version: Option<String>,
+    pub authors: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub repository: Option<String>,
+    pub readme: Option<String>,
+}
+
+impl Metadata {
+    /// Checks that the version is a semantic version and the license an SPDX expression.
+    pub fn validate(&self) -> FatalResult<()> {
+        if let Some(version) = &self.version {
+            validate_version(version)?;
+        }
+
+        if let Some(license) = &self.license {
+            validate_license(license)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn validate_version(version: &str) -> FatalResult<Version> {
+    match Version::parse(version) {
+        Ok(res) => Ok(res),
+        Err(err) => Err(FatalError::InvalidVersion {
+            version: version.to_string(),
+            err,
+        }),
+    }
+}
+
+pub fn validate_license(license: &str) -> FatalResult<()> {
+    match Expression::parse(license) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(FatalError::InvalidLicense { err }),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Profiles {
+    pub debug: Option<Profile>,
+    pub release: Option<Profile>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Profile {
+    pub sanitize: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Hooks {
+    pub pre_build: Option<Vec<String>>,
+    pub post_build: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Lint {
+    pub checks: Option<Vec<String>>,
+    pub clang_tidy: Option<bool>,
+    pub analyze: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ToolchainConfig {
+    pub kind: Option<ToolchainKind>,
+    pub zig_version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BinInfo {
+    pub name: String,
+    pub path: String,
+}
+
+/// A `[target.<spec>]` table.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct TargetTable {
+    #[serde(flatten)]
+    pub config: TargetConfig,
+    #[serde(rename = "override")]
+    pub overrides: Option<TargetConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct TargetConfig {
+    pub flags: Option<Vec<String>>,
+    pub link_flags: Option<Vec<String>>,
+    pub defines: Option<Vec<String>>,
+    pub sources: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub dependencies: Option<Vec<String>>,
+}
+
+impl TargetConfig {
+    fn extend(&mut self, other: &TargetConfig) {
+        extend_list(&mut self.flags, &other.flags);
+        extend_list(&mut self.link_flags, &other.link_flags);
+        extend_list(&mut self.defines, &other.defines);
+        extend_list(&mut self.sources, &other.sources);
+        extend_list(&mut self.exclude, &other.exclude);
+        extend_list(&mut self.dependencies, &other.dependencies);
+    }
+
+    fn replace(&mut self, other: &TargetConfig) {
+        replace_list(&mut self.flags, &other.flags);
+        replace_list(&mut self.link_flags, &other.link_flags);
+        replace_list(&mut self.defines, &other.defines);
+        replace_list(&mut self.sources, &other.sources);
+        replace_list(&mut self.exclude, &other.exclude);
+        replace_list(&mut self.dependencies, &other.dependencies);
+    }
+}
+
+fn extend_list(list: &mut Option<Vec<String>>, other: &Option<Vec<String>>) {
+    if let Some(other) = other {
+        list.get_or_insert_with(Vec::new)
+            .extend(other.iter().cloned());
+    }
+}
+
+fn replace_list(list: &mut Option<Vec<String>>, other: &Option<Vec<String>>) {
+    if other.is_some() {
+        list.clone_from(other);
+    }
 }
 
 pub fn parse_spork_file(path: &str) -> FatalResult<ProjectFile> {
@@ -52,3 +314,124 @@ impl Display for ProjectType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn target(spec: &str) -> Target {
+        Target::new(spec, false).unwrap()
+    }
+
+    fn project(tables: &str) -> ProjectFile {
+        let src = format!("[project]\nname = \"test\"\nkind = \"executable\"\n{tables}");
+        toml::from_str(&src).unwrap()
+    }
+
+    #[test]
+    fn matching_tables_extend_the_project() {
+        let project = project(
+            r#"
+            [target.'cfg(os = "windows")']
+            link_flags = ["-lws2_32"]
+
+            [target.x86_64-windows]
+            link_flags = ["-lbcrypt"]
+            sources = ["win32/**/*"]
+
+            [target.'cfg(os = "linux")']
+            link_flags = ["-lpthread"]
+            "#,
+        );
+
+        let config = project.target_config(&target("x86_64-windows")).unwrap();
+        assert_eq!(config.link_flags.unwrap(), ["-lws2_32", "-lbcrypt"]);
+        assert_eq!(config.sources.unwrap(), [DEFAULT_SOURCES, "win32/**/*"]);
+
+        let config = project.target_config(&target("x86_64-linux")).unwrap();
+        assert_eq!(config.link_flags.unwrap(), ["-lpthread"]);
+        assert_eq!(config.sources.unwrap(), [DEFAULT_SOURCES]);
+    }
+
+    #[test]
+    fn overrides_replace_earlier_settings() {
+        let project = project(
+            r#"
+            [target.'cfg(os = "windows")']
+            link_flags = ["-lws2_32"]
+
+            [target.x86_64-windows]
+            link_flags = ["-lbcrypt"]
+
+            [target.x86_64-windows.override]
+            link_flags = ["-lkernel32"]
+            sources = ["win32/**/*"]
+            "#,
+        );
+
+        let config = project.target_config(&target("x86_64-windows")).unwrap();
+        assert_eq!(config.link_flags.unwrap(), ["-lkernel32"]);
+        assert_eq!(config.sources.unwrap(), ["win32/**/*"]);
+
+        let config = project.target_config(&target("x86-windows")).unwrap();
+        assert_eq!(config.link_flags.unwrap(), ["-lws2_32"]);
+    }
+
+    #[test]
+    fn later_tables_extend_overrides() {
+        let project = project(
+            r#"
+            [target.'cfg(arch = "x86_64")'.override]
+            defines = ["WIDE"]
+
+            [target.x86_64-linux]
+            defines = ["LINUX"]
+            "#,
+        );
+
+        let config = project.target_config(&target("x86_64-linux")).unwrap();
+        assert_eq!(config.defines.unwrap(), ["WIDE", "LINUX"]);
+    }
+
+    #[test]
+    fn only_offered_features_can_be_enabled() {
+        let src = "[project]\nname = \"lib\"\nkind = \"library\"\nfeatures = [\"simd\", \"log\"]";
+        let lib: ProjectFile = toml::from_str(src).unwrap();
+
+        assert!(lib.project.check_features(&[]).is_ok());
+        assert!(lib.project.check_features(&[String::from("log")]).is_ok());
+        assert!(matches!(
+            lib.project.check_features(&[String::from("simd"), String::from("gpu")]),
+            Err(FatalError::NoSuchFeature { feature, .. }) if feature == "gpu"
+        ));
+
+        let plain = project("");
+        assert!(plain
+            .project
+            .check_features(&[String::from("simd")])
+            .is_err());
+    }
+
+    #[test]
+    fn loose_versions_only_fail_validation() {
+        let dir = env::temp_dir().join(format!("spork-project-loose-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Spork.toml");
+        fs::write(
+            &path,
+            "[project]\nname = \"old\"\nkind = \"library\"\nversion = \"1.0\"\n",
+        )
+        .unwrap();
+
+        let parsed = parse_spork_file(&path.to_string_lossy());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let spork_file = parsed.unwrap();
+        assert!(matches!(
+            spork_file.project.metadata().validate(),
+            Err(FatalError::InvalidVersion { version, .. }) if version == "1.0"
+        ));
+    }
+}
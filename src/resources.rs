@@ -0,0 +1,290 @@
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+use regex::Regex;
+
+use crate::{
+    build::{out_dir, BuildInfo},
+    error::{FatalError, FatalResult},
+    progress,
+    sources::glob_paths,
+    util::{mkdir_all, mkfile, modified},
+    SPORK_FILE_NAME,
+};
+
+pub const RESOURCES_HEADER_NAME: &str = "spork_resources.h";
+pub const RESOURCES_SOURCE_NAME: &str = "spork_resources.c";
+const RESOURCES_LIST_NAME: &str = "spork_resources.list";
+
+struct Resource {
+    symbol: String,
+    path: String,
+}
+
+/// Generates a header and a translation unit embedding the project's resources as C arrays,
+/// returning the directory they were written to.
+///
+/// Nothing is regenerated unless a resource or the spork file changed since the last build, or
+/// the resource globs match different files.
+pub fn generate_resources(info: &BuildInfo) -> FatalResult<Option<String>> {
+    if info.resources.is_empty() {
+        return Ok(None);
+    }
+
+    let resources = collect_resources(&info.resources)?;
+
+    let dir = format!("{}/resources/{}", out_dir(info), info.name);
+    let src_path = format!("{dir}/{RESOURCES_SOURCE_NAME}");
+    let list_path = format!("{dir}/{RESOURCES_LIST_NAME}");
+
+    // Deleting a resource doesn't make the others newer, so the files last embedded are compared
+    let list: String = resources
+        .iter()
+        .map(|resource| format!("{}\n", resource.path))
+        .collect();
+
+    let generated_time = modified(&src_path);
+    let is_up_to_date = fs::read_to_string(&list_path).is_ok_and(|existing| existing == list)
+        && resources
+            .iter()
+            .map(|resource| resource.path.as_str())
+            .chain([SPORK_FILE_NAME])
+            .all(|path| match (generated_time, modified(path)) {
+                (Some(generated_time), Some(input_time)) => input_time <= generated_time,
+                _ => false,
+            });
+
+    if is_up_to_date {
+        return Ok(Some(dir));
+    }
+
+    progress!("embedding {} resources...", resources.len());
+    mkdir_all(&dir)?;
+
+    let (header, src) = embed(&resources)?;
+    mkfile(&format!("{dir}/{RESOURCES_HEADER_NAME}"), &header)?;
+    mkfile(&src_path, &src)?;
+    mkfile(&list_path, &list)?;
+
+    Ok(Some(dir))
+}
+
+/// The header declaring the resources, and the source defining them.
+fn embed(resources: &[Resource]) -> FatalResult<(String, String)> {
+    let mut header = String::from("#pragma once\n\n#include <stddef.h>\n\n");
+    let mut src = format!("#include \"{RESOURCES_HEADER_NAME}\"\n");
+
+    for resource in resources {
+        let data = match fs::read(&resource.path) {
+            Ok(res) => res,
+            Err(err) => {
+                return Err(FatalError::CannotReadFile {
+                    path: resource.path.clone(),
+                    err,
+                })
+            }
+        };
+
+        let symbol = &resource.symbol;
+        writeln!(header, "// {}", resource.path).unwrap();
+        writeln!(header, "extern const unsigned char {symbol}[];").unwrap();
+        writeln!(header, "extern const size_t {symbol}_len;").unwrap();
+
+        // C doesn't allow empty arrays
+        writeln!(src, "\nconst unsigned char {symbol}[] = {{").unwrap();
+        if data.is_empty() {
+            src.push_str("    0x00,\n");
+        }
+        for chunk in data.chunks(16) {
+            src.push_str("   ");
+            for byte in chunk {
+                write!(src, " 0x{byte:02x},").unwrap();
+            }
+            src.push('\n');
+        }
+        writeln!(src, "}};\nconst size_t {symbol}_len = {};", data.len()).unwrap();
+    }
+
+    Ok((header, src))
+}
+
+fn collect_resources(mapping: &BTreeMap<String, String>) -> FatalResult<Vec<Resource>> {
+    let identifier_regex = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+    let non_identifier_regex = Regex::new(r"[^A-Za-z0-9_]").unwrap();
+
+    let mut resources = Vec::new();
+
+    for (pattern, symbol) in mapping {
+        if !identifier_regex.is_match(symbol) {
+            return Err(FatalError::InvalidResourceSymbol {
+                symbol: symbol.clone(),
+            });
+        }
+
+        // A glob gets one symbol per file, prefixed with the mapped name
+        let is_glob = pattern.contains(['*', '?', '[']);
+        if !is_glob {
+            if !Path::new(pattern).is_file() {
+                return Err(FatalError::NoResourceFiles {
+                    path: pattern.clone(),
+                });
+            }
+
+            resources.push(Resource {
+                symbol: symbol.clone(),
+                path: pattern.clone(),
+            });
+            continue;
+        }
+
+        let start_len = resources.len();
+        for path in glob_paths(pattern)?.flatten().filter(|path| path.is_file()) {
+            let file_name = path.file_name().unwrap().to_string_lossy();
+            resources.push(Resource {
+                symbol: format!(
+                    "{symbol}_{}",
+                    non_identifier_regex.replace_all(&file_name, "_")
+                ),
+                path: path.to_string_lossy().to_string(),
+            });
+        }
+
+        if resources.len() == start_len {
+            return Err(FatalError::NoResourceFiles {
+                path: pattern.clone(),
+            });
+        }
+    }
+
+    Ok(resources)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    /// Writes the given files to a fresh directory, returning its path.
+    fn resource_dir(name: &str, files: &[(&str, &[u8])]) -> String {
+        let dir = env::temp_dir().join(format!("spork-resources-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        for (file, data) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, data).unwrap();
+        }
+
+        dir.to_string_lossy().replace('\\', "/")
+    }
+
+    #[test]
+    fn embeds_files_as_arrays() {
+        let dir = resource_dir(
+            "embed",
+            &[("logo.bin", &[0xde, 0xad, 0xbe, 0xef]), ("empty.txt", b"")],
+        );
+        let resources = [
+            Resource {
+                symbol: String::from("logo"),
+                path: format!("{dir}/logo.bin"),
+            },
+            Resource {
+                symbol: String::from("empty"),
+                path: format!("{dir}/empty.txt"),
+            },
+        ];
+
+        let (header, src) = embed(&resources).unwrap();
+
+        assert!(header.starts_with("#pragma once\n\n#include <stddef.h>\n"));
+        assert!(
+            header.contains("extern const unsigned char logo[];\nextern const size_t logo_len;\n")
+        );
+        assert!(src.starts_with("#include \"spork_resources.h\"\n"));
+        assert!(src.contains(
+            "const unsigned char logo[] = {\n    0xde, 0xad, 0xbe, 0xef,\n};\nconst size_t logo_len = 4;\n"
+        ));
+        assert!(src.contains(
+            "const unsigned char empty[] = {\n    0x00,\n};\nconst size_t empty_len = 0;\n"
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn wraps_long_arrays() {
+        let dir = resource_dir("wrap", &[("data.bin", &[7; 17])]);
+        let resources = [Resource {
+            symbol: String::from("data"),
+            path: format!("{dir}/data.bin"),
+        }];
+
+        let (_, src) = embed(&resources).unwrap();
+        let rows: Vec<_> = src
+            .lines()
+            .filter(|line| line.starts_with("    0x"))
+            .collect();
+
+        assert_eq!(
+            rows,
+            [
+                format!("   {}", " 0x07,".repeat(16)),
+                String::from("    0x07,")
+            ]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn globs_get_a_symbol_per_file() {
+        let dir = resource_dir(
+            "glob",
+            &[
+                ("shaders/basic.vert", b""),
+                ("shaders/basic-2.frag", b""),
+                ("font.ttf", b""),
+            ],
+        );
+        let mapping = BTreeMap::from([
+            (format!("{dir}/shaders/*"), String::from("shader")),
+            (format!("{dir}/font.ttf"), String::from("font")),
+        ]);
+
+        let mut symbols: Vec<_> = collect_resources(&mapping)
+            .unwrap()
+            .into_iter()
+            .map(|resource| resource.symbol)
+            .collect();
+        symbols.sort();
+
+        assert_eq!(
+            symbols,
+            ["font", "shader_basic_2_frag", "shader_basic_vert"]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_bad_symbols_and_missing_files() {
+        let dir = resource_dir("errors", &[("a.txt", b"")]);
+
+        let mapping = BTreeMap::from([(format!("{dir}/a.txt"), String::from("2fast"))]);
+        assert!(matches!(
+            collect_resources(&mapping),
+            Err(FatalError::InvalidResourceSymbol { .. })
+        ));
+
+        for pattern in [format!("{dir}/missing.txt"), format!("{dir}/*.bin")] {
+            let mapping = BTreeMap::from([(pattern, String::from("data"))]);
+            assert!(matches!(
+                collect_resources(&mapping),
+                Err(FatalError::NoResourceFiles { .. })
+            ));
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
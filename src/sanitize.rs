@@ -0,0 +1,132 @@
+use std::fmt::Display;
+
+use crate::{
+    error::{FatalError, FatalResult},
+    targets::{OperatingSystem, Target},
+};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Sanitizer {
+    Address,
+    Undefined,
+    Thread,
+}
+
+impl Sanitizer {
+    pub fn new(name: &str) -> FatalResult<Self> {
+        Ok(match name {
+            "address" => Self::Address,
+            "undefined" => Self::Undefined,
+            "thread" => Self::Thread,
+            _ => {
+                return Err(FatalError::InvalidSanitizer {
+                    name: name.to_string(),
+                })
+            }
+        })
+    }
+
+    /// Parses a list of sanitizer names, rejecting combinations and targets they can't be used with.
+    pub fn parse_list(names: &[String], target: &Target) -> FatalResult<Vec<Self>> {
+        let mut sanitizers = names
+            .iter()
+            .map(|name| Self::new(name))
+            .collect::<FatalResult<Vec<_>>>()?;
+
+        sanitizers.sort();
+        sanitizers.dedup();
+
+        if let Some(sanitizer) = sanitizers.first() {
+            if target.os == OperatingSystem::Freestanding || target.os == OperatingSystem::Windows {
+                return Err(FatalError::UnsupportedSanitizer {
+                    name: sanitizer.to_string(),
+                    target: target.clone(),
+                });
+            }
+        }
+
+        // ThreadSanitizer and AddressSanitizer use incompatible runtimes
+        if sanitizers.contains(&Self::Address) && sanitizers.contains(&Self::Thread) {
+            return Err(FatalError::IncompatibleSanitizers {
+                names: vec![Self::Address.to_string(), Self::Thread.to_string()],
+            });
+        }
+
+        Ok(sanitizers)
+    }
+
+    pub fn cc_arg(sanitizers: &[Self]) -> Option<String> {
+        if sanitizers.is_empty() {
+            return None;
+        }
+
+        let names: Vec<_> = sanitizers.iter().map(|s| s.to_string()).collect();
+        Some(format!("-fsanitize={}", names.join(",")))
+    }
+}
+
+impl Display for Sanitizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Address => "address",
+                Self::Undefined => "undefined",
+                Self::Thread => "thread",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn linux() -> Target {
+        Target::new("x86_64-linux", false).unwrap()
+    }
+
+    #[test]
+    fn parses_sorted_and_deduplicated() {
+        let sanitizers =
+            Sanitizer::parse_list(&names(&["undefined", "address", "undefined"]), &linux())
+                .unwrap();
+
+        assert_eq!(sanitizers, [Sanitizer::Address, Sanitizer::Undefined]);
+        assert_eq!(
+            Sanitizer::cc_arg(&sanitizers).unwrap(),
+            "-fsanitize=address,undefined"
+        );
+        assert_eq!(Sanitizer::cc_arg(&[]), None);
+    }
+
+    #[test]
+    fn rejects_unknown_sanitizers() {
+        let err = Sanitizer::parse_list(&names(&["address", "memory"]), &linux()).unwrap_err();
+        assert!(matches!(err, FatalError::InvalidSanitizer { name } if name == "memory"));
+    }
+
+    #[test]
+    fn rejects_address_with_thread() {
+        let err = Sanitizer::parse_list(&names(&["thread", "address"]), &linux()).unwrap_err();
+        assert!(matches!(err, FatalError::IncompatibleSanitizers { .. }));
+
+        assert!(Sanitizer::parse_list(&names(&["thread", "undefined"]), &linux()).is_ok());
+    }
+
+    #[test]
+    fn rejects_unsupported_targets() {
+        for spec in ["x86_64-windows", "x86_64-freestanding"] {
+            let target = Target::new(spec, false).unwrap();
+
+            let err = Sanitizer::parse_list(&names(&["undefined"]), &target).unwrap_err();
+            assert!(matches!(err, FatalError::UnsupportedSanitizer { .. }));
+            assert_eq!(Sanitizer::parse_list(&[], &target).unwrap(), []);
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use std::path::{Component, Path};
+
+use glob::{glob, Paths, Pattern};
+
+use crate::{
+    error::{FatalError, FatalResult},
+    project::{BinInfo, ProjectFile, TargetConfig},
+    targets::{OperatingSystem, Target},
+    warning,
+};
+
+pub const DEFAULT_SOURCES: &str = "src/**/*";
+
+/// Collects the C files to compile for a target, in a stable order.
+///
+/// Files are matched by the `sources` globs, minus the `exclude` globs and any file that is
+/// specific to another OS - either by name (`foo.windows.c`) or by directory (`platform/windows/`).
+pub fn collect_sources(config: &TargetConfig, target: &Target) -> FatalResult<Vec<String>> {
+    let mut sources = Vec::new();
+
+    let exclude = match &config.exclude {
+        Some(exclude) => exclude
+            .iter()
+            .map(|pattern| new_pattern(pattern))
+            .collect::<FatalResult<Vec<_>>>()?,
+        None => Vec::new(),
+    };
+
+    for pattern in config.sources.iter().flatten() {
+        for path in glob_paths(pattern)? {
+            let path = match path {
+                Ok(res) => res,
+                Err(err) => {
+                    return Err(FatalError::CannotReadFileInDir {
+                        path: err.path().to_string_lossy().to_string(),
+                        err: err.into(),
+                    })
+                }
+            };
+
+            if !path.is_file() || exclude.iter().any(|pattern| pattern.matches_path(&path)) {
+                continue;
+            }
+
+            let file = match path.to_str() {
+                Some(res) => res.to_string(),
+                None => {
+                    return Err(FatalError::FileInvalidUTF8 {
+                        path: path.as_path().into(),
+                    })
+                }
+            };
+
+            if !(file.ends_with(".c") || file.ends_with(".h")) {
+                warning!(
+                    "spork can only compile C files (*.c, *.h) - consider removing file '{file}'"
+                );
+                continue;
+            }
+
+            if file.ends_with(".h") {
+                // TODO : proper handling of h files
+                continue;
+            }
+
+            if is_for_target(&path, target) && !sources.contains(&file) {
+                sources.push(file);
+            }
+        }
+    }
+
+    Ok(sources)
+}
+
+/// Collects the executables of a project from its `[[bin]]` tables and `src/bin/*.c` files.
+/// Files for another OS are skipped, and `src/bin/tool.windows.c` builds `tool`.
+///
+/// When there is more than one executable, `src/main.c` becomes the root of the one named after
+/// the project.
+pub fn collect_bins(spork_file: &ProjectFile, target: &Target) -> FatalResult<Vec<BinInfo>> {
+    let mut bins = spork_file.bin.clone().unwrap_or_default();
+
+    for path in glob_paths("src/bin/*.c")?.flatten() {
+        if !is_for_target(&path, target) {
+            continue;
+        }
+
+        let (Some(name), Some(path)) = (bin_name(&path), path.to_str()) else {
+            return Err(FatalError::FileInvalidUTF8 {
+                path: path.as_path().into(),
+            });
+        };
+
+        if !bins.iter().any(|bin| bin.name == name || bin.path == path) {
+            bins.push(BinInfo {
+                name: name.to_string(),
+                path: path.to_string(),
+            });
+        }
+    }
+
+    let name = &spork_file.project.name;
+    if !bins.is_empty()
+        && Path::new("src/main.c").is_file()
+        && !bins.iter().any(|bin| &bin.name == name)
+    {
+        bins.push(BinInfo {
+            name: name.clone(),
+            path: String::from("src/main.c"),
+        });
+    }
+
+    Ok(bins)
+}
+
+pub struct ExtraBin {
+    pub name: String,
+    pub sources: Vec<String>,
+}
+
+/// Collects standalone executables from a directory such as `examples` or `tests` - each
+/// `<dir>/*.c` file and `<dir>/*/` directory is built into its own executable.
+pub fn collect_extra_bins(dir: &str, target: &Target) -> FatalResult<Vec<ExtraBin>> {
+    let mut bins = Vec::new();
+
+    for path in glob_paths(&format!("{dir}/*"))?.flatten() {
+        let (Some(stem), Some(name)) = (
+            path.file_stem().and_then(|stem| stem.to_str()),
+            bin_name(&path),
+        ) else {
+            return Err(FatalError::FileInvalidUTF8 {
+                path: path.as_path().into(),
+            });
+        };
+
+        let pattern = if path.is_dir() {
+            format!("{dir}/{stem}/**/*.c")
+        } else if path.extension().is_some_and(|ext| ext == "c") && is_for_target(&path, target) {
+            format!("{dir}/{stem}.c")
+        } else {
+            continue;
+        };
+
+        let config = TargetConfig {
+            sources: Some(vec![pattern]),
+            ..Default::default()
+        };
+
+        bins.push(ExtraBin {
+            name: name.to_string(),
+            sources: collect_sources(&config, target)?,
+        });
+    }
+
+    Ok(bins)
+}
+
+/// The name of the executable built from a file - its stem without an OS suffix, so that
+/// `tool.windows.c` and `tool.linux.c` both build `tool`.
+fn bin_name(path: &Path) -> Option<&str> {
+    let stem = path.file_stem()?.to_str()?;
+
+    match stem.rsplit_once('.') {
+        Some((name, os)) if OperatingSystem::new(os).is_ok() => Some(name),
+        _ => Some(stem),
+    }
+}
+
+fn is_for_target(path: &Path, target: &Target) -> bool {
+    let is_other_os = |os: &str| match OperatingSystem::new(os) {
+        Ok(os) => os != target.os,
+        Err(_) => false,
+    };
+
+    // foo.windows.c
+    if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+        if let Some((_, os)) = stem.rsplit_once('.') {
+            if is_other_os(os) {
+                return false;
+            }
+        }
+    }
+
+    // platform/windows/foo.c
+    let components: Vec<_> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect();
+
+    !components
+        .windows(2)
+        .any(|pair| pair[0] == "platform" && is_other_os(pair[1]))
+}
+
+pub fn glob_paths(pattern: &str) -> FatalResult<Paths> {
+    match glob(pattern) {
+        Ok(res) => Ok(res),
+        Err(err) => Err(FatalError::InvalidGlob {
+            path: pattern.to_string(),
+            err,
+        }),
+    }
+}
+
+fn new_pattern(pattern: &str) -> FatalResult<Pattern> {
+    match Pattern::new(pattern) {
+        Ok(res) => Ok(res),
+        Err(err) => Err(FatalError::InvalidGlob {
+            path: pattern.to_string(),
+            err,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn target(spec: &str) -> Target {
+        Target::new(spec, false).unwrap()
+    }
+
+    /// Creates the given (empty) files in a fresh directory, returning its path.
+    fn project_dir(name: &str, files: &[&str]) -> String {
+        let dir = env::temp_dir().join(format!("spork-sources-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        for file in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        dir.to_string_lossy().replace('\\', "/")
+    }
+
+    #[test]
+    fn os_suffixes_select_files() {
+        let linux = target("x86_64-linux");
+
+        assert!(is_for_target(Path::new("src/net.c"), &linux));
+        assert!(is_for_target(Path::new("src/net.linux.c"), &linux));
+        assert!(!is_for_target(Path::new("src/net.windows.c"), &linux));
+        assert!(!is_for_target(Path::new("src/net.freestanding.c"), &linux));
+    }
+
+    #[test]
+    fn dotted_stems_that_arent_oses_are_kept() {
+        let windows = target("x86_64-windows");
+
+        assert!(is_for_target(Path::new("src/foo.bar.c"), &windows));
+        assert!(is_for_target(Path::new("src/v1.2.c"), &windows));
+    }
+
+    #[test]
+    fn platform_directories_select_files() {
+        let windows = target("x86_64-windows");
+
+        assert!(is_for_target(
+            Path::new("src/platform/windows/io.c"),
+            &windows
+        ));
+        assert!(!is_for_target(
+            Path::new("src/platform/linux/io.c"),
+            &windows
+        ));
+        assert!(!is_for_target(
+            Path::new("src/a/platform/linux/b/io.c"),
+            &windows
+        ));
+
+        // Only directories directly below a `platform` directory count
+        assert!(is_for_target(Path::new("src/linux/io.c"), &windows));
+        assert!(is_for_target(
+            Path::new("src/platform/common/io.c"),
+            &windows
+        ));
+    }
+
+    #[test]
+    fn collects_sources_for_target() {
+        let dir = project_dir(
+            "collect",
+            &[
+                "src/main.c",
+                "src/util.h",
+                "src/foo.bar.c",
+                "src/net.windows.c",
+                "src/net.linux.c",
+                "src/platform/windows/io.c",
+                "src/platform/linux/io.c",
+                "src/gen/skip.c",
+                "src/notes.txt",
+            ],
+        );
+
+        let config = TargetConfig {
+            sources: Some(vec![format!("{dir}/src/**/*"), format!("{dir}/src/main.c")]),
+            exclude: Some(vec![format!("{dir}/src/gen/*")]),
+            ..Default::default()
+        };
+
+        let relative = |target_spec| {
+            let mut files: Vec<_> = collect_sources(&config, &target(target_spec))
+                .unwrap()
+                .into_iter()
+                .map(|file| file.replace('\\', "/")[dir.len() + 1..].to_string())
+                .collect();
+            files.sort();
+            files
+        };
+
+        assert_eq!(
+            relative("x86_64-linux"),
+            [
+                "src/foo.bar.c",
+                "src/main.c",
+                "src/net.linux.c",
+                "src/platform/linux/io.c",
+            ]
+        );
+        assert_eq!(
+            relative("x86_64-windows"),
+            [
+                "src/foo.bar.c",
+                "src/main.c",
+                "src/net.windows.c",
+                "src/platform/windows/io.c",
+            ]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bin_names_drop_os_suffixes() {
+        assert_eq!(bin_name(Path::new("src/bin/tool.c")), Some("tool"));
+        assert_eq!(bin_name(Path::new("src/bin/tool.windows.c")), Some("tool"));
+        assert_eq!(bin_name(Path::new("src/bin/tool.v2.c")), Some("tool.v2"));
+    }
+
+    #[test]
+    fn collects_extra_bins_for_target() {
+        let dir = project_dir(
+            "extra",
+            &[
+                "examples/hello.c",
+                "examples/net.windows.c",
+                "examples/net.linux.c",
+                "examples/multi/main.c",
+                "examples/multi/io.windows.c",
+            ],
+        );
+
+        let bins = |target_spec| {
+            let mut bins: Vec<_> =
+                collect_extra_bins(&format!("{dir}/examples"), &target(target_spec))
+                    .unwrap()
+                    .into_iter()
+                    .map(|bin| (bin.name, bin.sources.len()))
+                    .collect();
+            bins.sort();
+            bins
+        };
+
+        let expected = [
+            (String::from("hello"), 1),
+            (String::from("multi"), 1),
+            (String::from("net"), 1),
+        ];
+        assert_eq!(bins("x86_64-linux"), expected);
+        assert_eq!(
+            bins("x86_64-windows"),
+            [
+                (String::from("hello"), 1),
+                (String::from("multi"), 2),
+                (String::from("net"), 1),
+            ]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,6 +1,9 @@
-use std::{fmt::Display, process::Command};
+use std::fmt::Display;
 
-use crate::error::{FatalError, FatalResult};
+use crate::{
+    error::{FatalError, FatalResult},
+    toolchain::toolchain,
+};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Target {
@@ -9,17 +12,21 @@ pub struct Target {
 }
 
 impl Target {
+    /// Parses a target in the `arch-os` format spork uses.
+    ///
+    /// A lenient parse accepts the triples printed by compilers instead, such as
+    /// `x86_64-pc-linux-gnu`, `i686-w64-mingw32` or `x86_64-unknown-none-elf`.
     pub fn new(target: &str, lenient: bool) -> FatalResult<Self> {
-        let mut triple_comps: Vec<_> = target.split('-').collect();
+        let triple_comps: Vec<_> = target.split('-').collect();
 
-        if !lenient {
-            if triple_comps.len() != 2 {
-                return Err(FatalError::BadTarget {
-                    target: target.to_string(),
-                });
-            }
-        } else {
-            triple_comps.remove(1);
+        if lenient {
+            return Self::from_triple(target, &triple_comps);
+        }
+
+        if triple_comps.len() != 2 {
+            return Err(FatalError::BadTarget {
+                target: target.to_string(),
+            });
         }
 
         Ok(Target {
@@ -28,16 +35,33 @@ impl Target {
         })
     }
 
-    pub fn host() -> FatalResult<Self> {
-        match Command::new("zig").args(["cc", "-dumpmachine"]).output() {
-            Ok(output) => {
-                let output = String::from_utf8_lossy(&output.stdout);
-                Ok(Target::new(&output, true)?)
-            }
-            Err(err) => Err(FatalError::FailedRunZigcc { err }),
+    fn from_triple(target: &str, triple_comps: &[&str]) -> FatalResult<Self> {
+        let arch = match triple_comps[0] {
+            "x86_64" | "amd64" => Architecture::X86_64,
+            "x86" | "i386" | "i486" | "i586" | "i686" => Architecture::X86,
+            arch => Architecture::new(arch)?,
+        };
+
+        // The vendor is optional, so the OS is whichever component is recognised
+        let os = triple_comps[1..].iter().find_map(|comp| match *comp {
+            "linux" => Some(OperatingSystem::Linux),
+            "windows" | "mingw32" | "win32" => Some(OperatingSystem::Windows),
+            "none" | "freestanding" | "elf" => Some(OperatingSystem::Freestanding),
+            _ => None,
+        });
+
+        match os {
+            Some(os) => Ok(Target { arch, os }),
+            None => Err(FatalError::BadTarget {
+                target: target.to_string(),
+            }),
         }
     }
 
+    pub fn host() -> FatalResult<Self> {
+        toolchain().host()
+    }
+
     pub fn ziggified(&self) -> String {
         format!("{}-{}", self.arch, self.os.ziggified())
     }
@@ -0,0 +1,80 @@
+use crate::{
+    build::{build, output_cmd, ArtifactKind, BuildOptions},
+    coverage,
+    error::{FatalError, FatalResult},
+    fatal_error,
+    message::redirect_stdout,
+    success,
+    targets::Target,
+};
+
+/// Builds every executable in `tests` and runs it - a test passes when it exits successfully.
+pub fn test(options: &BuildOptions) -> FatalResult<()> {
+    let infos = build(&BuildOptions {
+        tests: true,
+        ..options.clone()
+    })?;
+
+    let host = Target::host()?;
+    let mut has_run = false;
+    let mut passed = 0;
+    let mut failed = Vec::new();
+
+    for info in &infos {
+        if info.target != host {
+            continue;
+        }
+        has_run = true;
+
+        if options.coverage {
+            coverage::prepare(info)?;
+        }
+
+        for test in info
+            .outputs
+            .iter()
+            .filter(|artifact| artifact.kind == ArtifactKind::Test)
+        {
+            let mut cmd = output_cmd(&test.path, info);
+            if options.coverage {
+                cmd.env(
+                    "LLVM_PROFILE_FILE",
+                    coverage::profile_file(info, &test.name),
+                );
+            }
+            redirect_stdout(&mut cmd);
+
+            match cmd.status() {
+                Ok(status) if status.success() => {
+                    success!("test '{}' passed", test.name);
+                    passed += 1;
+                }
+                Ok(_) => {
+                    fatal_error!("test '{}' failed", test.name);
+                    failed.push(test.name.clone());
+                }
+                Err(err) => {
+                    return Err(FatalError::FailedRunOutput {
+                        path: test.path.clone(),
+                        err,
+                    })
+                }
+            }
+        }
+
+        if options.coverage {
+            coverage::report(info)?;
+        }
+    }
+
+    if !has_run {
+        return Err(FatalError::NoSupportedTargets);
+    }
+
+    if failed.is_empty() {
+        success!("{passed} tests passed");
+        Ok(())
+    } else {
+        Err(FatalError::TestsFailed { names: failed })
+    }
+}
@@ -0,0 +1,467 @@
+use std::{env, fmt::Display, path::Path, process::Command, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{FatalError, FatalResult},
+    project::ToolchainConfig,
+    targets::{Architecture, OperatingSystem, Target},
+};
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolchainKind {
+    #[default]
+    Zig,
+    Clang,
+    Gcc,
+}
+
+/// The C compiler and archiver a build uses.
+///
+/// `CC` and `AR` override the programs of the configured kind, and may include arguments
+/// (e.g. `CC="zig cc"`).
+#[derive(Debug, Clone)]
+pub struct Toolchain {
+    pub kind: ToolchainKind,
+    pub zig_version: Option<String>,
+    cc: Vec<String>,
+    ar: Vec<String>,
+    host: OnceLock<Target>,
+    version: OnceLock<String>,
+}
+
+static TOOLCHAIN: OnceLock<Toolchain> = OnceLock::new();
+
+/// Selects the toolchain for the rest of the run. Only the first call has an effect, so the root
+/// project's toolchain is also used for its dependencies.
+pub fn set_toolchain(config: Option<&ToolchainConfig>) {
+    TOOLCHAIN.get_or_init(|| match config {
+        Some(config) => Toolchain::new(config.kind, config.zig_version.clone()),
+        None => Toolchain::new(None, None),
+    });
+}
+
+pub fn toolchain() -> &'static Toolchain {
+    TOOLCHAIN.get_or_init(|| Toolchain::new(None, None))
+}
+
+impl Toolchain {
+    pub fn new(kind: Option<ToolchainKind>, zig_version: Option<String>) -> Self {
+        Self::with_env(kind, zig_version, env::var("CC").ok(), env::var("AR").ok())
+    }
+
+    /// A toolchain for the given values of `CC` and `AR`.
+    fn with_env(
+        kind: Option<ToolchainKind>,
+        zig_version: Option<String>,
+        cc_var: Option<String>,
+        ar_var: Option<String>,
+    ) -> Self {
+        let cc_var = cc_var.filter(|cc| !cc.trim().is_empty());
+        let ar_var = ar_var.filter(|ar| !ar.trim().is_empty());
+
+        // Without a configured kind, `CC` decides which flags the compiler understands
+        let kind = match (kind, &cc_var) {
+            (Some(kind), _) => kind,
+            (None, Some(cc)) if cc.contains("zig") => ToolchainKind::Zig,
+            (None, Some(cc)) if cc.contains("clang") => ToolchainKind::Clang,
+            (None, Some(_)) => ToolchainKind::Gcc,
+            (None, None) => ToolchainKind::default(),
+        };
+
+        let (cc, ar): (&[&str], &[&str]) = match kind {
+            ToolchainKind::Zig => (&["zig", "cc"], &["zig", "ar"]),
+            ToolchainKind::Clang => (&["clang"], &["ar"]),
+            ToolchainKind::Gcc => (&["gcc"], &["ar"]),
+        };
+
+        let program = |value: Option<String>, default: &[&str]| match value {
+            Some(value) => value.split_whitespace().map(String::from).collect(),
+            None => default.iter().map(|arg| arg.to_string()).collect(),
+        };
+        Self {
+            kind,
+            zig_version,
+            cc: program(cc_var, cc),
+            ar: program(ar_var, ar),
+            host: OnceLock::new(),
+            version: OnceLock::new(),
+        }
+    }
+
+    /// The compiler command, e.g. `zig cc` - used for compiling and linking.
+    pub fn cc_cmd(&self) -> Command {
+        let mut cmd = Command::new(&self.cc[0]);
+        cmd.args(&self.cc[1..]);
+        cmd
+    }
+
+    /// The compiler program, without any arguments.
+    pub fn cc_program(&self) -> &str {
+        &self.cc[0]
+    }
+
+    pub fn cc_name(&self) -> String {
+        self.cc.join(" ")
+    }
+
+    /// The compiler's version - `zig version` for zig, or the first line of `--version` otherwise.
+    /// The compiler is only asked once per run.
+    pub fn version(&self) -> FatalResult<String> {
+        if let Some(version) = self.version.get() {
+            return Ok(version.clone());
+        }
+
+        let mut cmd = if self.kind == ToolchainKind::Zig {
+            let mut cmd = Command::new(self.cc_program());
+            cmd.arg("version");
+            cmd
+        } else {
+            let mut cmd = self.cc_cmd();
+            cmd.arg("--version");
+            cmd
+        };
+
+        let version = match cmd.output() {
+            Ok(output) => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+            Err(err) => {
+                return Err(FatalError::FailedRunCompiler {
+                    cc: self.cc_name(),
+                    err,
+                })
+            }
+        };
+
+        Ok(self.version.get_or_init(|| version).clone())
+    }
+
+    /// Checks the installed zig against the `zig_version` the project is pinned to, if any.
+    ///
+    /// A pinned version matches any version it is a prefix of, so `0.11` accepts `0.11.0`.
+    pub fn check_zig_version(&self) -> FatalResult<()> {
+        let Some(expected) = &self.zig_version else {
+            return Ok(());
+        };
+
+        if self.kind != ToolchainKind::Zig {
+            return Ok(());
+        }
+
+        let found = self.version()?;
+        if !zig_version_matches(expected, &found) {
+            return Err(FatalError::ZigVersionMismatch {
+                expected: expected.clone(),
+                found,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Whether `cc` compiles Windows resource scripts passed to it like sources, which zig does
+    /// since 0.12. Other toolchains need a separate resource compiler.
+    pub fn cc_compiles_resources(&self) -> FatalResult<bool> {
+        if self.kind != ToolchainKind::Zig {
+            return Ok(false);
+        }
+
+        Ok(zig_version_at_least(&self.version()?, [0, 12]))
+    }
+
+    /// The resource compiler matching the toolchain, for when `cc` can't compile resources.
+    pub fn windres_name(&self) -> &'static str {
+        match self.kind {
+            ToolchainKind::Clang => "llvm-windres",
+            ToolchainKind::Zig | ToolchainKind::Gcc => "windres",
+        }
+    }
+
+    /// Whether the toolchain can build for targets other than the host.
+    pub fn can_cross_compile(&self) -> bool {
+        self.kind != ToolchainKind::Gcc
+    }
+
+    pub fn host(&self) -> FatalResult<Target> {
+        if let Some(host) = self.host.get() {
+            return Ok(host.clone());
+        }
+
+        let host = match self.cc_cmd().arg("-dumpmachine").output() {
+            Ok(output) => Target::new(String::from_utf8_lossy(&output.stdout).trim(), true)?,
+            Err(err) => {
+                return Err(FatalError::FailedRunCompiler {
+                    cc: self.cc_name(),
+                    err,
+                })
+            }
+        };
+
+        Ok(self.host.get_or_init(|| host).clone())
+    }
+
+    /// The triple the compiler knows a target by, e.g. `x86_64-linux-gnu` for zig or
+    /// `x86_64-unknown-linux-gnu` for clang and gcc.
+    pub fn target_triple(&self, target: &Target) -> String {
+        match self.kind {
+            ToolchainKind::Zig => target.ziggified(),
+            ToolchainKind::Clang | ToolchainKind::Gcc => llvm_triple(target),
+        }
+    }
+
+    /// The flags that select a target.
+    ///
+    /// Native builds need none, except with zig which always gets an explicit target so that it
+    /// builds against its bundled libc.
+    pub fn target_args(&self, target: &Target) -> FatalResult<Vec<String>> {
+        if self.kind == ToolchainKind::Zig {
+            return Ok(vec![String::from("-target"), target.ziggified()]);
+        }
+
+        if target == &self.host()? {
+            return Ok(Vec::new());
+        }
+
+        if !self.can_cross_compile() {
+            return Err(FatalError::UnsupportedToolchainTarget {
+                kind: self.kind.to_string(),
+                target: target.clone(),
+            });
+        }
+
+        Ok(vec![format!("--target={}", self.target_triple(target))])
+    }
+
+    /// The flags instrumenting code for source-based coverage, which gcc doesn't support.
+    pub fn coverage_args(&self) -> FatalResult<Vec<String>> {
+        if self.kind == ToolchainKind::Gcc {
+            return Err(FatalError::UnsupportedToolchainCoverage {
+                kind: self.kind.to_string(),
+            });
+        }
+
+        Ok(["-fprofile-instr-generate", "-fcoverage-mapping"]
+            .map(String::from)
+            .to_vec())
+    }
+
+    /// The link flags for a shared library - with an import library for Windows. Versioned
+    /// libraries get a `lib<name>.so.MAJOR` soname, or the major and minor version in the DLL's PE
+    /// header (its `VERSIONINFO` resource is linked separately).
+    pub fn shared_lib_args(
+        &self,
+        output_path: &str,
+        target: &Target,
+        version: Option<[u64; 3]>,
+    ) -> Vec<String> {
+        let mut args = vec![String::from("-shared")];
+
+        match (&target.os, version) {
+            (OperatingSystem::Windows, _) => {
+                let import_lib_path = output_path.replace(".dll", ".lib");
+                args.push(format!("-Wl,--out-implib,{import_lib_path}"));
+
+                if let Some([major, minor, _]) = version {
+                    args.push(format!("-Wl,--major-image-version,{major}"));
+                    args.push(format!("-Wl,--minor-image-version,{minor}"));
+                }
+            }
+            (OperatingSystem::Linux, Some([major, ..])) => {
+                let file_name = Path::new(output_path).file_name().unwrap_or_default();
+                args.push(format!(
+                    "-Wl,-soname,{}.{major}",
+                    file_name.to_string_lossy()
+                ));
+            }
+            _ => {}
+        }
+
+        args
+    }
+
+    /// The command that bundles objects into a static library.
+    pub fn archive_cmd(&self, output_path: &str, objects: &[String]) -> Command {
+        let mut cmd = Command::new(&self.ar[0]);
+        cmd.args(&self.ar[1..]);
+        cmd.args(["rcs", output_path]).args(objects);
+        cmd
+    }
+
+    pub fn ar_name(&self) -> String {
+        self.ar.join(" ")
+    }
+}
+
+impl Display for ToolchainKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Zig => "zig",
+                Self::Clang => "clang",
+                Self::Gcc => "gcc",
+            }
+        )
+    }
+}
+
+/// Whether a pinned zig version is a prefix of the found one, comparing whole components and
+/// ignoring pre-release and build suffixes (`0.12.0-dev.1+abc` is `0.12.0`).
+fn zig_version_matches(expected: &str, found: &str) -> bool {
+    let found_release = found.split(['-', '+']).next().unwrap_or_default();
+
+    let expected_comps: Vec<_> = expected.split('.').collect();
+    let found_comps: Vec<_> = found_release.split('.').collect();
+
+    expected_comps.len() <= found_comps.len()
+        && expected_comps.iter().zip(&found_comps).all(|(a, b)| a == b)
+}
+
+/// Whether a zig version is at least `MAJOR.MINOR`. Development builds count as the release
+/// they lead up to, so `0.12.0-dev.1+abc` is at least 0.12.
+fn zig_version_at_least(found: &str, [major, minor]: [u64; 2]) -> bool {
+    let found_release = found.split(['-', '+']).next().unwrap_or_default();
+    let mut comps = found_release
+        .split('.')
+        .map(|comp| comp.parse::<u64>().unwrap_or_default());
+
+    let found = [
+        comps.next().unwrap_or_default(),
+        comps.next().unwrap_or_default(),
+    ];
+    found >= [major, minor]
+}
+
+fn llvm_triple(target: &Target) -> String {
+    let arch = match target.arch {
+        Architecture::X86 => "i686",
+        Architecture::X86_64 => "x86_64",
+    };
+
+    let rest = match target.os {
+        OperatingSystem::Freestanding => "unknown-none-elf",
+        OperatingSystem::Windows => "w64-windows-gnu",
+        OperatingSystem::Linux => "unknown-linux-gnu",
+    };
+
+    format!("{arch}-{rest}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_cc(kind: Option<ToolchainKind>, cc: Option<&str>) -> Toolchain {
+        Toolchain::with_env(kind, None, cc.map(String::from), None)
+    }
+
+    #[test]
+    fn kinds_have_default_programs() {
+        let zig = with_cc(None, None);
+        assert_eq!(zig.kind, ToolchainKind::Zig);
+        assert_eq!(zig.cc_name(), "zig cc");
+        assert_eq!(zig.ar_name(), "zig ar");
+
+        let gcc = with_cc(Some(ToolchainKind::Gcc), None);
+        assert_eq!(gcc.cc_name(), "gcc");
+        assert_eq!(gcc.ar_name(), "ar");
+    }
+
+    #[test]
+    fn cc_selects_the_kind() {
+        assert_eq!(with_cc(None, Some("zig cc")).kind, ToolchainKind::Zig);
+        assert_eq!(
+            with_cc(None, Some("/usr/bin/clang-17")).kind,
+            ToolchainKind::Clang
+        );
+        assert_eq!(with_cc(None, Some("cc")).kind, ToolchainKind::Gcc);
+        assert_eq!(with_cc(None, Some("  ")).kind, ToolchainKind::Zig);
+    }
+
+    #[test]
+    fn triples_match_the_toolchain() {
+        let target = Target::new("x86-windows", false).unwrap();
+
+        assert_eq!(
+            with_cc(None, None).target_triple(&target),
+            target.ziggified()
+        );
+        assert_eq!(
+            with_cc(Some(ToolchainKind::Clang), None).target_triple(&target),
+            "i686-w64-windows-gnu"
+        );
+    }
+
+    #[test]
+    fn cc_overrides_the_program_but_not_a_configured_kind() {
+        let toolchain = with_cc(Some(ToolchainKind::Clang), Some("ccache  clang -m64"));
+
+        assert_eq!(toolchain.kind, ToolchainKind::Clang);
+        assert_eq!(toolchain.cc_program(), "ccache");
+        assert_eq!(toolchain.cc_name(), "ccache clang -m64");
+    }
+
+    #[test]
+    fn pinned_zig_versions_are_prefixes() {
+        assert!(zig_version_matches("0.11", "0.11.0"));
+        assert!(zig_version_matches("0.11.0", "0.11.0"));
+        assert!(zig_version_matches("0", "0.11.0"));
+        assert!(zig_version_matches("0.12.0", "0.12.0-dev.3180+83e578a18"));
+
+        assert!(!zig_version_matches("0.11", "0.12.0"));
+        assert!(!zig_version_matches("0.1", "0.11.0"));
+        assert!(!zig_version_matches("0.11.0.1", "0.11.0"));
+        assert!(!zig_version_matches("0.11", ""));
+    }
+
+    #[test]
+    fn zig_compiles_resources_since_0_12() {
+        assert!(zig_version_at_least("0.12.0", [0, 12]));
+        assert!(zig_version_at_least("0.12.0-dev.3180+83e578a18", [0, 12]));
+        assert!(zig_version_at_least("0.13.0", [0, 12]));
+        assert!(zig_version_at_least("1.0.0", [0, 12]));
+
+        assert!(!zig_version_at_least("0.11.0", [0, 12]));
+        assert!(!zig_version_at_least("0.9.1", [0, 12]));
+        assert!(!zig_version_at_least("", [0, 12]));
+    }
+
+    #[test]
+    fn shared_libraries_are_versioned() {
+        let toolchain = with_cc(None, None);
+        let linux = Target::new("x86_64-linux", false).unwrap();
+        let windows = Target::new("x86_64-windows", false).unwrap();
+
+        assert_eq!(
+            toolchain.shared_lib_args("out/libfoo.so", &linux, Some([1, 2, 3])),
+            ["-shared", "-Wl,-soname,libfoo.so.1"]
+        );
+        assert_eq!(
+            toolchain.shared_lib_args("out/libfoo.so", &linux, None),
+            ["-shared"]
+        );
+        assert_eq!(
+            toolchain.shared_lib_args("out/foo.dll", &windows, Some([1, 2, 3])),
+            [
+                "-shared",
+                "-Wl,--out-implib,out/foo.lib",
+                "-Wl,--major-image-version,1",
+                "-Wl,--minor-image-version,2"
+            ]
+        );
+    }
+
+    #[test]
+    fn ar_overrides_the_archiver() {
+        let toolchain = Toolchain::with_env(None, None, None, Some(String::from("llvm-ar")));
+
+        assert_eq!(toolchain.cc_name(), "zig cc");
+        assert_eq!(toolchain.ar_name(), "llvm-ar");
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+
+use crate::{
+    build::{Dependencies, Dependency},
+    error::{FatalError, FatalResult},
+    project::{parse_spork_file, ProjectType},
+    success,
+    targets::Target,
+    toolchain::set_toolchain,
+    util::canonical_key,
+    SPORK_FILE_NAME,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TreeFormat {
+    Text,
+    Dot,
+}
+
+struct Node {
+    name: String,
+    kind: ProjectType,
+    path: String,
+    targets: Option<Vec<String>>,
+    children: Vec<usize>,
+    parents: Vec<usize>,
+}
+
+impl Node {
+    fn label(&self) -> String {
+        let targets = match &self.targets {
+            Some(targets) => targets.join(", "),
+            None => String::from("any target"),
+        };
+
+        format!("{} ({}, {}) [{targets}]", self.name, self.kind, self.path)
+    }
+}
+
+/// The dependency graph of a project, where each project directory is one node.
+struct Graph {
+    nodes: Vec<Node>,
+    ids: BTreeMap<String, usize>,
+}
+
+impl Graph {
+    fn add(&mut self, dep: &Dependency, parent: usize) -> usize {
+        let key = canonical_key(&dep.path);
+
+        let id = match self.ids.get(&key) {
+            Some(id) => *id,
+            None => {
+                let id = self.nodes.len();
+                self.ids.insert(key, id);
+                self.nodes.push(Node {
+                    name: dep.name.clone(),
+                    kind: dep.kind,
+                    path: dep.path.clone(),
+                    targets: dep.targets.clone(),
+                    children: Vec::new(),
+                    parents: Vec::new(),
+                });
+
+                if let Some(deps) = &dep.deps {
+                    for child in deps.iter() {
+                        self.add(child, id);
+                    }
+                }
+
+                id
+            }
+        };
+
+        if !self.nodes[parent].children.contains(&id) {
+            self.nodes[parent].children.push(id);
+            self.nodes[id].parents.push(parent);
+        }
+
+        id
+    }
+
+    /// Finds a node by its path, or by name - which must be unique.
+    fn find(&self, name_or_path: &str) -> FatalResult<usize> {
+        let key = canonical_key(name_or_path);
+        if let Some(&id) = self.ids.get(&key) {
+            return Ok(id);
+        }
+
+        let matches: Vec<_> = (0..self.nodes.len())
+            .filter(|&id| self.nodes[id].name == name_or_path)
+            .collect();
+
+        match matches[..] {
+            [] => Err(FatalError::NoSuchDependency {
+                name: name_or_path.to_string(),
+            }),
+            [id] => Ok(id),
+            _ => Err(FatalError::AmbiguousDependency {
+                name: name_or_path.to_string(),
+                paths: matches
+                    .iter()
+                    .map(|&id| self.nodes[id].path.clone())
+                    .collect(),
+            }),
+        }
+    }
+
+    fn edges(&self, id: usize, invert: bool) -> &[usize] {
+        if invert {
+            &self.nodes[id].parents
+        } else {
+            &self.nodes[id].children
+        }
+    }
+}
+
+/// Prints the dependency graph of the project for a target - by default the first target of the
+/// project, or the host.
+pub fn tree(
+    target: Option<&str>,
+    invert: Option<&str>,
+    duplicates: bool,
+    format: TreeFormat,
+) -> FatalResult<()> {
+    let spork_file = parse_spork_file(SPORK_FILE_NAME)?;
+    set_toolchain(spork_file.toolchain.as_ref());
+
+    let target = match target {
+        Some(target) => Target::new(target, false)?,
+        None => spork_file.default_target()?,
+    };
+
+    let mut graph = Graph {
+        nodes: vec![Node {
+            name: spork_file.project.name.clone(),
+            kind: spork_file.project.kind,
+            path: String::from("."),
+            targets: spork_file.project.targets.clone(),
+            children: Vec::new(),
+            parents: Vec::new(),
+        }],
+        ids: BTreeMap::from([(canonical_key("."), 0)]),
+    };
+
+    if let Some(deps) = spork_file.target_config(&target)?.dependencies {
+        let deps = Dependencies::new(deps, &target, spork_file.dependency_features.as_ref())?;
+        for dep in deps.iter() {
+            graph.add(dep, 0);
+        }
+    }
+
+    if duplicates {
+        print_duplicates(&graph);
+        return Ok(());
+    }
+
+    let root = match invert {
+        Some(name) => graph.find(name)?,
+        None => 0,
+    };
+
+    match format {
+        TreeFormat::Text => {
+            println!("{}", graph.nodes[root].label());
+            let mut expanded = vec![false; graph.nodes.len()];
+            expanded[root] = true;
+            print_children(&graph, root, invert.is_some(), "", &mut expanded);
+        }
+        TreeFormat::Dot => print_dot(&graph, root, invert.is_some()),
+    }
+
+    Ok(())
+}
+
+/// Prints a node's children - a node that already had its children printed is marked with `(*)`
+/// instead of being expanded again.
+fn print_children(graph: &Graph, id: usize, invert: bool, prefix: &str, expanded: &mut [bool]) {
+    let edges = graph.edges(id, invert);
+
+    for (i, &child) in edges.iter().enumerate() {
+        let is_last = i == edges.len() - 1;
+        let (branch, indent) = if is_last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        let node = &graph.nodes[child];
+        if expanded[child] && !graph.edges(child, invert).is_empty() {
+            println!("{prefix}{branch}{} (*)", node.label());
+            continue;
+        }
+
+        println!("{prefix}{branch}{}", node.label());
+        expanded[child] = true;
+        print_children(graph, child, invert, &format!("{prefix}{indent}"), expanded);
+    }
+}
+
+fn print_dot(graph: &Graph, root: usize, invert: bool) {
+    let mut reachable = vec![false; graph.nodes.len()];
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        if !reachable[id] {
+            reachable[id] = true;
+            stack.extend(graph.edges(id, invert));
+        }
+    }
+
+    println!("digraph dependencies {{");
+
+    for (id, node) in graph.nodes.iter().enumerate() {
+        if reachable[id] {
+            println!(
+                "    n{id} [label=\"{}\\n{}\\n{}\"];",
+                escape_dot(&node.name),
+                node.kind,
+                escape_dot(&node.path)
+            );
+        }
+    }
+
+    // Edges always point from a project to its dependency
+    for (id, node) in graph.nodes.iter().enumerate() {
+        for &child in &node.children {
+            if reachable[id] && reachable[child] {
+                println!("    n{id} -> n{child};");
+            }
+        }
+    }
+
+    println!("}}");
+}
+
+/// Escapes a string for a quoted Graphviz ID.
+fn escape_dot(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Prints libraries that are pulled in from more than one directory, which would be linked twice
+/// under the same name.
+fn print_duplicates(graph: &Graph) {
+    let mut by_name: BTreeMap<&str, Vec<&Node>> = BTreeMap::new();
+    for node in &graph.nodes {
+        by_name.entry(&node.name).or_default().push(node);
+    }
+
+    let mut found = false;
+    for nodes in by_name.values().filter(|nodes| nodes.len() > 1) {
+        found = true;
+
+        for node in nodes {
+            let parents: Vec<_> = node
+                .parents
+                .iter()
+                .map(|&parent| graph.nodes[parent].name.as_str())
+                .collect();
+            println!("{} (required by {})", node.label(), parents.join(", "));
+        }
+        println!();
+    }
+
+    if !found {
+        success!("no duplicate dependencies");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, path: &str) -> Node {
+        Node {
+            name: name.to_string(),
+            kind: ProjectType::library,
+            path: path.to_string(),
+            targets: None,
+            children: Vec::new(),
+            parents: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn duplicate_names_need_a_path() {
+        let dirs = ["/nonexistent/app", "/nonexistent/a", "/nonexistent/b/a"];
+        let graph = Graph {
+            nodes: vec![node("app", dirs[0]), node("a", dirs[1]), node("a", dirs[2])],
+            ids: dirs
+                .iter()
+                .enumerate()
+                .map(|(id, path)| (path.to_string(), id))
+                .collect(),
+        };
+
+        assert_eq!(graph.find("app").unwrap(), 0);
+        assert_eq!(graph.find("/nonexistent/b/a").unwrap(), 2);
+        assert!(matches!(
+            graph.find("a"),
+            Err(FatalError::AmbiguousDependency { paths, .. }) if paths == dirs[1..]
+        ));
+        assert!(matches!(
+            graph.find("c"),
+            Err(FatalError::NoSuchDependency { .. })
+        ));
+    }
+
+    #[test]
+    fn dot_labels_are_escaped() {
+        assert_eq!(escape_dot(r#"C:\libs\"a""#), r#"C:\\libs\\\"a\""#);
+    }
+}
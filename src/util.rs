@@ -1,8 +1,4 @@
-use std::{
-    env,
-    fs::{self, DirEntry},
-    path::Path,
-};
+use std::{env, fs, path::Path, process::Command, sync::OnceLock, time::SystemTime};
 
 use regex::Regex;
 
@@ -19,6 +15,16 @@ pub fn mkfile(path: &str, contents: &str) -> FatalResult<()> {
     }
 }
 
+/// Like `mkfile`, but leaves the file (and its modification time) alone if it already has
+/// these contents.
+pub fn write_if_changed(path: &str, contents: &str) -> FatalResult<()> {
+    if fs::read_to_string(path).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+
+    mkfile(path, contents)
+}
+
 pub fn mkdir(path: &str) -> FatalResult<()> {
     match Path::new(path).try_exists() {
         Ok(exists) => {
@@ -55,59 +61,44 @@ pub fn mkdir_all(path: &str) -> FatalResult<()> {
     }
 }
 
-pub fn walkdir(path: &str) -> FatalResult<Vec<String>> {
-    let mut output = Vec::new();
+/// Creates (or replaces) a symlink at `link` pointing to `original`, which is relative to the
+/// link's directory. Without symlink support, the file is copied instead.
+pub fn symlink(original: &str, link: &str) -> FatalResult<()> {
+    let _ = fs::remove_file(link);
 
-    let path_dir = match fs::read_dir(path) {
-        Ok(res) => res,
-        Err(err) => {
-            return Err(FatalError::CannotReadDir {
-                path: path.to_string(),
-                err,
-            })
-        }
-    };
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(original, link);
 
-    for entry in path_dir {
-        let entry = match entry {
-            Ok(res) => res,
-            Err(err) => {
-                return Err(FatalError::CannotReadFileInDir {
-                    path: path.to_string(),
-                    err,
-                })
-            }
-        };
-
-        let entry_metadata = match entry.metadata() {
-            Ok(res) => res,
-            Err(err) => {
-                return Err(FatalError::CannotReadFileInDir {
-                    path: path.to_string(),
-                    err,
-                })
-            }
-        };
+    #[cfg(not(unix))]
+    let result = {
+        let dir = Path::new(link).parent().unwrap_or(Path::new("."));
+        fs::copy(dir.join(original), link).map(|_| ())
+    };
 
-        if entry_metadata.is_dir() {
-            output.append(&mut walkdir(&entry_to_string(&entry)?)?);
-        } else {
-            output.push(entry_to_string(&entry)?);
-        }
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) => Err(FatalError::CannotCreateFile {
+            path: link.to_string(),
+            err,
+        }),
     }
-
-    Ok(output)
 }
 
-fn entry_to_string(entry: &DirEntry) -> FatalResult<String> {
-    match entry.path().to_str() {
-        Some(res) => Ok(res.to_string()),
-        None => Err(FatalError::FileInvalidUTF8 {
-            path: entry.path().into(),
-        }),
+/// A key identifying the directory at a path however it was reached, e.g. `../a` and `../b/../a`
+/// - or the path itself if it doesn't exist.
+pub fn canonical_key(path: &str) -> String {
+    match fs::canonicalize(path) {
+        Ok(res) => res.to_string_lossy().to_string(),
+        Err(_) => path.to_string(),
     }
 }
 
+pub fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
 pub fn check_project_name(name: &str) -> FatalResult<()> {
     let verifier = Regex::new(r"[^a-z_]").unwrap();
     if verifier.is_match(name) {
@@ -117,8 +108,30 @@ pub fn check_project_name(name: &str) -> FatalResult<()> {
     }
 }
 
-pub static mut LAUNCH_DIR: String = String::new();
+static LAUNCH_DIR: OnceLock<String> = OnceLock::new();
 
 pub fn update_launch_dir() {
-    unsafe { LAUNCH_DIR = env::current_dir().unwrap().to_string_lossy().to_string() }
+    LAUNCH_DIR.get_or_init(|| env::current_dir().unwrap().to_string_lossy().to_string());
+}
+
+pub fn launch_dir() -> &'static str {
+    LAUNCH_DIR.get().map(String::as_str).unwrap_or_default()
+}
+
+/// A command for an external tool, which can be overridden by an environment variable.
+pub fn tool_cmd(env_var: &str, default: &str) -> Command {
+    Command::new(env::var(env_var).unwrap_or_else(|_| default.to_string()))
+}
+
+pub fn run_tool(cmd: &mut Command, tool: &str) -> FatalResult<()> {
+    match cmd.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(FatalError::ToolFailed {
+            tool: tool.to_string(),
+        }),
+        Err(err) => Err(FatalError::FailedRunTool {
+            tool: tool.to_string(),
+            err,
+        }),
+    }
 }
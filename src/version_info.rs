@@ -0,0 +1,178 @@
+use std::fmt::Write;
+
+use semver::Version;
+
+use crate::{
+    build::{out_dir, BuildInfo},
+    error::FatalResult,
+    project::Metadata,
+    targets::Architecture,
+    toolchain::{toolchain, ToolchainKind},
+    util::{mkdir_all, run_tool, tool_cmd, write_if_changed},
+    warning,
+};
+
+const VERSION_RESOURCE_NAME: &str = "spork_version.rc";
+
+// Values from winver.h, which resource compilers other than windres can't always find
+const VS_FF_DEBUG: u32 = 0x1;
+const VS_FF_PRERELEASE: u32 = 0x2;
+const VOS_NT_WINDOWS32: u32 = 0x40004;
+const VFT_DLL: u32 = 0x2;
+
+/// Generates a resource script with a `VERSIONINFO` resource for a library's DLL, and compiles it
+/// unless the compiler does so itself - returning the file to link, if any.
+///
+/// zig compiles resource scripts passed to it since 0.12. Other toolchains compile them with
+/// `windres` (`llvm-windres` for clang), which `WINDRES` overrides.
+pub fn generate_version_resource(
+    info: &BuildInfo,
+    version: &Version,
+) -> FatalResult<Option<String>> {
+    let dir = format!("{}/version_info/{}", out_dir(info), info.name);
+    let rc_path = format!("{dir}/{VERSION_RESOURCE_NAME}");
+
+    let toolchain = toolchain();
+    if toolchain.kind == ToolchainKind::Zig && !toolchain.cc_compiles_resources()? {
+        warning!(
+            "zig {} can't compile resources - '{}.dll' won't have version info (needs zig 0.12)",
+            toolchain.version()?,
+            info.name
+        );
+        return Ok(None);
+    }
+
+    mkdir_all(&dir)?;
+    write_if_changed(
+        &rc_path,
+        &version_script(&info.name, &info.metadata, version, info.release),
+    )?;
+
+    if toolchain.kind == ToolchainKind::Zig {
+        return Ok(Some(rc_path));
+    }
+
+    let obj_path = format!("{dir}/spork_version.o");
+    let windres = toolchain.windres_name();
+    let bfd_target = match info.target.arch {
+        Architecture::X86 => "pe-i386",
+        Architecture::X86_64 => "pe-x86-64",
+    };
+
+    let mut cmd = tool_cmd("WINDRES", windres);
+    cmd.args([
+        "-O", "coff", "--target", bfd_target, "-i", &rc_path, "-o", &obj_path,
+    ]);
+    run_tool(&mut cmd, windres)?;
+
+    Ok(Some(obj_path))
+}
+
+fn version_script(name: &str, metadata: &Metadata, version: &Version, release: bool) -> String {
+    let numeric = format!("{},{},{},0", version.major, version.minor, version.patch);
+
+    let mut flags = 0;
+    if !release {
+        flags |= VS_FF_DEBUG;
+    }
+    if !version.pre.is_empty() {
+        flags |= VS_FF_PRERELEASE;
+    }
+
+    let mut strings = vec![
+        (
+            "FileDescription",
+            metadata.description.clone().unwrap_or(name.to_string()),
+        ),
+        ("FileVersion", version.to_string()),
+        ("InternalName", name.to_string()),
+        ("OriginalFilename", format!("{name}.dll")),
+        ("ProductName", name.to_string()),
+        ("ProductVersion", version.to_string()),
+    ];
+    if let Some(authors) = &metadata.authors {
+        strings.push(("CompanyName", authors.join(", ")));
+    }
+    if let Some(license) = &metadata.license {
+        strings.push(("LegalCopyright", license.clone()));
+    }
+
+    let mut script = String::from("// Generated by spork - do not edit\n\n");
+    writeln!(script, "1 VERSIONINFO").unwrap();
+    writeln!(script, "FILEVERSION {numeric}").unwrap();
+    writeln!(script, "PRODUCTVERSION {numeric}").unwrap();
+    writeln!(script, "FILEFLAGSMASK 0x3f").unwrap();
+    writeln!(script, "FILEFLAGS {flags:#x}").unwrap();
+    writeln!(script, "FILEOS {VOS_NT_WINDOWS32:#x}").unwrap();
+    writeln!(script, "FILETYPE {VFT_DLL:#x}").unwrap();
+    writeln!(script, "FILESUBTYPE 0x0").unwrap();
+    script.push_str("BEGIN\n    BLOCK \"StringFileInfo\"\n    BEGIN\n");
+
+    // US English, Unicode
+    script.push_str("        BLOCK \"040904b0\"\n        BEGIN\n");
+    for (key, value) in strings {
+        writeln!(
+            script,
+            "            VALUE \"{key}\", \"{}\"",
+            escape(&value)
+        )
+        .unwrap();
+    }
+    script.push_str("        END\n    END\n");
+    script.push_str("    BLOCK \"VarFileInfo\"\n    BEGIN\n");
+    script.push_str("        VALUE \"Translation\", 0x409, 1200\n    END\nEND\n");
+
+    script
+}
+
+/// Escapes a value for a resource script string, where quotes are doubled. Control characters
+/// can't be escaped portably, so they become spaces.
+fn escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '"' => String::from("\"\""),
+            '\\' => String::from("\\\\"),
+            c if c.is_control() => String::from(" "),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_hold_the_version() {
+        let version = Version::parse("1.2.3").unwrap();
+        let script = version_script("foo", &Metadata::default(), &version, true);
+
+        assert!(script.contains("\nFILEVERSION 1,2,3,0\nPRODUCTVERSION 1,2,3,0\n"));
+        assert!(script.contains("\nFILEFLAGS 0x0\n"));
+        assert!(script.contains("\nFILETYPE 0x2\n"));
+        assert!(script.contains("VALUE \"FileDescription\", \"foo\"\n"));
+        assert!(script.contains("VALUE \"FileVersion\", \"1.2.3\"\n"));
+        assert!(script.contains("VALUE \"OriginalFilename\", \"foo.dll\"\n"));
+        assert!(!script.contains("CompanyName"));
+    }
+
+    #[test]
+    fn scripts_hold_the_metadata() {
+        let version = Version::parse("2.0.0-beta.1").unwrap();
+        let metadata = Metadata {
+            authors: Some(vec![String::from("Ann"), String::from("Bo \"B\"")]),
+            description: Some(String::from("A C:\\ library\nfor things")),
+            license: Some(String::from("MIT")),
+            ..Default::default()
+        };
+        let script = version_script("foo", &metadata, &version, false);
+
+        assert!(script.contains("\nFILEVERSION 2,0,0,0\n"));
+        assert!(script.contains("\nFILEFLAGS 0x3\n"));
+        assert!(script.contains("VALUE \"ProductVersion\", \"2.0.0-beta.1\"\n"));
+        assert!(script.contains(r#"VALUE "FileDescription", "A C:\\ library for things""#));
+        assert!(script.contains(r#"VALUE "CompanyName", "Ann, Bo ""B""""#));
+        assert!(script.contains(r#"VALUE "LegalCopyright", "MIT""#));
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Child,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::{
+    build::{build, build_for_run, BuildOptions, Dependencies},
+    error::{FatalError, FatalResult},
+    message::report_error,
+    progress,
+    project::parse_spork_file,
+    test,
+    toolchain::set_toolchain,
+    warning, SPORK_FILE_NAME,
+};
+
+/// How long the watched files must be left alone before the command runs again, so that a burst
+/// of writes (e.g. saving many files at once) only triggers one run.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+const WATCH_DIRS: [&str; 4] = ["src", "include", "tests", "examples"];
+const WATCH_FILES: [&str; 2] = [SPORK_FILE_NAME, "build.c"];
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WatchCommand {
+    Build,
+    Run,
+    Test,
+    Check,
+}
+
+/// Runs a command, then runs it again whenever the project or one of its path dependencies
+/// changes. A running executable is killed before it's restarted.
+pub fn watch(command: WatchCommand, options: &BuildOptions) -> FatalResult<()> {
+    let spork_file = parse_spork_file(SPORK_FILE_NAME)?;
+    set_toolchain(spork_file.toolchain.as_ref());
+
+    let target = spork_file.default_target()?;
+    let mut project_dirs = vec![String::from(".")];
+    if let Some(deps) = spork_file.target_config(&target)?.dependencies {
+        project_dirs.extend(
+            Dependencies::new(deps, &target, spork_file.dependency_features.as_ref())?.paths(),
+        );
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(sender) {
+        Ok(res) => res,
+        Err(err) => {
+            return Err(FatalError::CannotWatch {
+                path: String::from("."),
+                err,
+            })
+        }
+    };
+
+    // Whole project directories are watched so that e.g. a 'tests' directory created later is
+    // picked up - changes outside the watched files, such as the build outputs in 'bin', are
+    // filtered out when they arrive
+    let mut roots = Vec::new();
+    for project_dir in &project_dirs {
+        roots.push(canonicalize(project_dir)?);
+    }
+    roots.sort();
+    roots.dedup();
+
+    for (i, root) in roots.iter().enumerate() {
+        // A dependency inside another project's directory is already covered by its watch
+        if !roots[..i].iter().any(|parent| root.starts_with(parent)) {
+            watch_path(&mut watcher, root, RecursiveMode::Recursive)?;
+        }
+    }
+
+    let mut child = None;
+    loop {
+        if let Some(mut child) = child.take() {
+            stop_child(&mut child);
+        }
+
+        child = run_command(command, options);
+
+        progress!("watching for changes...");
+        if !wait_for_change(&receiver, &roots) {
+            return Ok(());
+        }
+    }
+}
+
+fn run_command(command: WatchCommand, options: &BuildOptions) -> Option<Child> {
+    let result = match command {
+        WatchCommand::Build => build(options).map(|_| None),
+        WatchCommand::Check => build(&BuildOptions {
+            check: true,
+            ..options.clone()
+        })
+        .map(|_| None),
+        WatchCommand::Test => test::test(options).map(|_| None),
+        WatchCommand::Run => {
+            build_for_run(options, None, None).and_then(|mut cmd| match cmd.spawn() {
+                Ok(child) => Ok(Some(child)),
+                Err(err) => Err(FatalError::FailedRunOutput {
+                    path: cmd.get_program().to_string_lossy().to_string(),
+                    err,
+                }),
+            })
+        }
+    };
+
+    match result {
+        Ok(child) => child,
+        Err(err) => {
+            report_error(&err);
+            None
+        }
+    }
+}
+
+fn stop_child(child: &mut Child) {
+    if let Ok(None) = child.try_wait() {
+        progress!("stopping previous run...");
+
+        if let Err(err) = child.kill() {
+            warning!("couldn't stop previous run: {err}");
+        }
+    }
+
+    let _ = child.wait();
+}
+
+/// Blocks until a watched file changes and no more changes follow within the debounce time.
+/// Returns false if the watcher stopped.
+fn wait_for_change(receiver: &Receiver<notify::Result<Event>>, roots: &[PathBuf]) -> bool {
+    loop {
+        match receiver.recv() {
+            Ok(Ok(event)) if is_relevant(&event, roots) => break,
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => warning!("error while watching files: {err}"),
+            Err(_) => return false,
+        }
+    }
+
+    loop {
+        match receiver.recv_timeout(DEBOUNCE) {
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => return true,
+            Err(RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+}
+
+fn is_relevant(event: &Event, roots: &[PathBuf]) -> bool {
+    if !matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    ) {
+        return false;
+    }
+
+    event.paths.iter().any(|path| is_watched(path, roots))
+}
+
+/// Whether a path is one of a project's watched files, or in one of its watched directories.
+fn is_watched(path: &Path, roots: &[PathBuf]) -> bool {
+    roots.iter().any(|root| {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+
+        let mut components = relative.components();
+        let Some(first) = components
+            .next()
+            .and_then(|first| first.as_os_str().to_str())
+        else {
+            return false;
+        };
+
+        WATCH_DIRS.contains(&first) || (components.next().is_none() && WATCH_FILES.contains(&first))
+    })
+}
+
+fn watch_path(watcher: &mut impl Watcher, path: &Path, mode: RecursiveMode) -> FatalResult<()> {
+    match watcher.watch(path, mode) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(FatalError::CannotWatch {
+            path: path.to_string_lossy().to_string(),
+            err,
+        }),
+    }
+}
+
+fn canonicalize(path: &str) -> FatalResult<PathBuf> {
+    match fs::canonicalize(path) {
+        Ok(res) => Ok(res),
+        Err(err) => Err(FatalError::CannotReadDir {
+            path: path.to_string(),
+            err,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_project_files_are_watched() {
+        let roots = [PathBuf::from("/p"), PathBuf::from("/p/deps/d")];
+
+        assert!(is_watched(Path::new("/p/src/main.c"), &roots));
+        assert!(is_watched(Path::new("/p/tests"), &roots));
+        assert!(is_watched(Path::new("/p/Spork.toml"), &roots));
+        assert!(is_watched(Path::new("/p/deps/d/include/d.h"), &roots));
+        assert!(is_watched(Path::new("/p/deps/d/build.c"), &roots));
+
+        assert!(!is_watched(Path::new("/p"), &roots));
+        assert!(!is_watched(
+            Path::new("/p/bin/x86_64-linux/debug/p"),
+            &roots
+        ));
+        assert!(!is_watched(Path::new("/p/README.md"), &roots));
+        assert!(!is_watched(Path::new("/p/docs/Spork.toml"), &roots));
+        assert!(!is_watched(
+            Path::new("/p/deps/d/bin/x86_64-linux/debug/d.o"),
+            &roots
+        ));
+        assert!(!is_watched(Path::new("/other/src/main.c"), &roots));
+    }
+}
@@ -0,0 +1,111 @@
+// Benchmark runner bundled by spork - linked into every benchmark executable.
+//
+// Each benchmark is warmed up, then timed over many samples. Every line starting with
+// 'spork-bench' holds the time per iteration of each sample in nanoseconds, for spork to report.
+
+#define _POSIX_C_SOURCE 199309L
+
+#include <limits.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "spork_bench.h"
+
+#ifdef _WIN32
+#include <windows.h>
+#else
+#include <time.h>
+#endif
+
+#define SPORK_BENCH_MAX 256
+#define SPORK_BENCH_WARMUP_NS 100000000.0
+#define SPORK_BENCH_SAMPLE_NS 10000000.0
+#define SPORK_BENCH_DEFAULT_SAMPLES 50
+// Keeps the iteration count representable however fast a benchmark is
+#define SPORK_BENCH_MAX_ITERATIONS ((double)(LLONG_MAX / 2))
+
+static struct {
+    const char *name;
+    spork_bench_fn fn;
+} spork_benches[SPORK_BENCH_MAX];
+static int spork_bench_count = 0;
+
+void spork_bench_register(const char *name, spork_bench_fn fn) {
+    if (spork_bench_count == SPORK_BENCH_MAX) {
+        fprintf(stderr, "too many benchmarks - only %d are supported\n", SPORK_BENCH_MAX);
+        exit(1);
+    }
+
+    spork_benches[spork_bench_count].name = name;
+    spork_benches[spork_bench_count].fn = fn;
+    spork_bench_count++;
+}
+
+static double spork_bench_now_ns(void) {
+#ifdef _WIN32
+    LARGE_INTEGER frequency, counter;
+    QueryPerformanceFrequency(&frequency);
+    QueryPerformanceCounter(&counter);
+    return (double)counter.QuadPart * 1e9 / (double)frequency.QuadPart;
+#else
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return (double)ts.tv_sec * 1e9 + (double)ts.tv_nsec;
+#endif
+}
+
+static double spork_bench_time(spork_bench_fn fn, long long iterations) {
+    double start = spork_bench_now_ns();
+    for (long long i = 0; i < iterations; i++) {
+        fn();
+    }
+    return spork_bench_now_ns() - start;
+}
+
+static void spork_bench_run(const char *name, spork_bench_fn fn, int samples) {
+    // Warm up caches and branch predictors, doubling the iterations until enough time has passed
+    long long iterations = 1;
+    double elapsed = 0.0;
+    double warmup_elapsed = 0.0;
+    long long warmup_iterations = 0;
+    while (warmup_elapsed < SPORK_BENCH_WARMUP_NS) {
+        elapsed = spork_bench_time(fn, iterations);
+        warmup_elapsed += elapsed;
+        warmup_iterations += iterations;
+        iterations *= 2;
+    }
+
+    // Each sample runs for roughly the same amount of time
+    double per_iteration = warmup_elapsed / (double)warmup_iterations;
+    double sample_iterations = SPORK_BENCH_SAMPLE_NS / (per_iteration > 0.0 ? per_iteration : 1.0);
+    if (sample_iterations > SPORK_BENCH_MAX_ITERATIONS) {
+        sample_iterations = SPORK_BENCH_MAX_ITERATIONS;
+    }
+    iterations = sample_iterations < 1.0 ? 1 : (long long)sample_iterations;
+
+    printf("spork-bench %s", name);
+    for (int i = 0; i < samples; i++) {
+        printf(" %.3f", spork_bench_time(fn, iterations) / (double)iterations);
+    }
+    printf("\n");
+    fflush(stdout);
+}
+
+int main(int argc, char **argv) {
+    const char *filter = argc > 1 ? argv[1] : NULL;
+
+    int samples = SPORK_BENCH_DEFAULT_SAMPLES;
+    const char *samples_env = getenv("SPORK_BENCH_SAMPLES");
+    if (samples_env != NULL && atoi(samples_env) > 0) {
+        samples = atoi(samples_env);
+    }
+
+    for (int i = 0; i < spork_bench_count; i++) {
+        if (filter == NULL || strstr(spork_benches[i].name, filter) != NULL) {
+            spork_bench_run(spork_benches[i].name, spork_benches[i].fn, samples);
+        }
+    }
+
+    return 0;
+}
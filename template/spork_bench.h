@@ -0,0 +1,34 @@
+#pragma once
+
+// Benchmark harness bundled by spork - see 'spork bench'.
+//
+//     #include <spork_bench.h>
+//
+//     SPORK_BENCH(sum_array) {
+//         int sum = 0;
+//         for (int i = 0; i < 1000; i++) sum += i;
+//         SPORK_BLACK_BOX(sum);
+//     }
+
+typedef void (*spork_bench_fn)(void);
+
+void spork_bench_register(const char *name, spork_bench_fn fn);
+
+// Keeps the compiler from optimizing away a value or the work that produced it
+static inline void spork_black_box(const volatile void *ptr) {
+#if defined(__GNUC__) || defined(__clang__)
+    __asm__ volatile("" : : "r"(ptr) : "memory");
+#else
+    (void)ptr;
+#endif
+}
+
+#define SPORK_BLACK_BOX(value) spork_black_box((const volatile void *)&(value))
+
+// Defines a benchmark, which is registered before main runs
+#define SPORK_BENCH(name)                                                        \
+    static void name(void);                                                      \
+    __attribute__((constructor)) static void spork_bench_register_##name(void) { \
+        spork_bench_register(#name, name);                                       \
+    }                                                                            \
+    static void name(void)